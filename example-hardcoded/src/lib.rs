@@ -1,12 +1,23 @@
+use libnss::alias::{Alias, AliasHooks};
+use libnss::ethers::{Ether, EthersHooks};
 use libnss::group::{Group, GroupHooks};
+use libnss::gshadow::{GShadow, GShadowHooks};
 use libnss::host::{AddressFamily, Addresses, Host, HostHooks};
-use libnss::initgroups::InitgroupsHooks;
-use libnss::interop::Response;
+use libnss::initgroups::InitgroupsBackend;
+use libnss::interop::{Enumeration, EnumerationMode, Response};
+use libnss::netgroup::{NetgroupEntry, NetgroupHooks, NetgroupTriple};
+use libnss::network::{Network, NetworkHooks};
 use libnss::passwd::{Passwd, PasswdHooks};
-use libnss::shadow::{Shadow, ShadowHooks};
+use libnss::protocol::{Protocol, ProtocolHooks};
+use libnss::rpc::{Rpc, RpcHooks};
+use libnss::service::{Service, ServiceHooks};
+use libnss::shadow::{Shadow, ShadowBackend};
+use libnss::subid::{SubidHooks, SubidRange, SubidType};
 use libnss::{
-    libnss_group_hooks, libnss_host_hooks, libnss_initgroups_hooks, libnss_passwd_hooks,
-    libnss_shadow_hooks,
+    libnss_alias_hooks, libnss_ethers_hooks, libnss_group_hooks, libnss_gshadow_hooks,
+    libnss_host_hooks, libnss_initgroups_hooks, libnss_netgroup_hooks, libnss_network_hooks,
+    libnss_passwd_hooks, libnss_protocol_hooks, libnss_rpc_hooks, libnss_service_hooks,
+    libnss_shadow_hooks, libnss_subid_hooks,
 };
 
 struct HardcodedPasswd;
@@ -73,6 +84,24 @@ impl GroupHooks for HardcodedGroup {
         }])
     }
 
+    // Entries can also be produced lazily while the caller enumerates, e.g. page by page
+    fn open_enumeration() -> Response<Enumeration<Group>> {
+        let entries = (1005..1006).map(|gid| {
+            Response::Success(Group {
+                name: "test".to_string(),
+                passwd: "".to_string(),
+                gid,
+                members: vec!["someone".to_string()],
+            })
+        });
+
+        Response::Success(Box::new(entries))
+    }
+
+    fn enumeration_mode() -> EnumerationMode {
+        EnumerationMode::Thread
+    }
+
     fn get_entry_by_gid(gid: libc::gid_t) -> Response<Group> {
         if gid == 1005 {
             return Response::Success(Group {
@@ -100,11 +129,35 @@ impl GroupHooks for HardcodedGroup {
     }
 }
 
-struct HardcodedShadow;
-libnss_shadow_hooks!(hardcoded, HardcodedShadow);
+// Shadow and initgroups are served by a single backend object, created on first use
+struct HardcodedBackend {
+    initgroups: Vec<Group>,
+}
+
+impl HardcodedBackend {
+    fn new() -> Self {
+        let group = |name: &str, gid| Group {
+            name: name.to_string(),
+            passwd: "".to_string(),
+            gid,
+            members: vec!["someone".to_string()],
+        };
+
+        HardcodedBackend {
+            initgroups: vec![
+                group("initgroup1", 3005),
+                group("initgroup2", 3006),
+                group("initgroup3", 3007),
+            ],
+        }
+    }
+}
+
+libnss_shadow_hooks!(hardcoded, HardcodedBackend = HardcodedBackend::new());
+libnss_initgroups_hooks!(hardcoded, HardcodedBackend = HardcodedBackend::new());
 
-impl ShadowHooks for HardcodedShadow {
-    fn get_all_entries() -> Response<Vec<Shadow>> {
+impl ShadowBackend for HardcodedBackend {
+    fn get_all_entries(&self) -> Response<Vec<Shadow>> {
         // TODO: Ensure we are a privileged user before returning results
         Response::Success(vec![
             Shadow {
@@ -121,7 +174,7 @@ impl ShadowHooks for HardcodedShadow {
         ])
     }
 
-    fn get_entry_by_name(name: String) -> Response<Shadow> {
+    fn get_entry_by_name(&self, name: String) -> Response<Shadow> {
         // TODO: Ensure we are a privileged user before returning results
         if name == "test" {
             return Response::Success(Shadow {
@@ -141,7 +194,36 @@ impl ShadowHooks for HardcodedShadow {
     }
 }
 
-use std::net::{IpAddr, Ipv4Addr};
+struct HardcodedGShadow;
+libnss_gshadow_hooks!(hardcoded, HardcodedGShadow);
+
+impl GShadowHooks for HardcodedGShadow {
+    fn get_all_entries() -> Response<Vec<GShadow>> {
+        // TODO: Ensure we are a privileged user before returning results
+        Response::Success(vec![GShadow {
+            name: "test".to_string(),
+            passwd: "!".to_string(),
+            admins: vec!["test".to_string()],
+            members: vec!["someone".to_string()],
+        }])
+    }
+
+    fn get_entry_by_name(name: String) -> Response<GShadow> {
+        // TODO: Ensure we are a privileged user before returning results
+        if name == "test" {
+            return Response::Success(GShadow {
+                name: "test".to_string(),
+                passwd: "!".to_string(),
+                admins: vec!["test".to_string()],
+                members: vec!["someone".to_string()],
+            });
+        }
+
+        Response::NotFound
+    }
+}
+
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
 
 struct HardcodedHost;
 libnss_host_hooks!(hardcoded, HardcodedHost);
@@ -152,6 +234,7 @@ impl HostHooks for HardcodedHost {
             name: "test.example".to_string(),
             addresses: Addresses::V4(vec![Ipv4Addr::new(177, 42, 42, 42)]),
             aliases: vec!["other.example".to_string()],
+            ttl: None,
         }])
     }
 
@@ -163,6 +246,7 @@ impl HostHooks for HardcodedHost {
                         name: "test.example".to_string(),
                         addresses: Addresses::V4(vec![Ipv4Addr::new(177, 42, 42, 42)]),
                         aliases: vec!["other.example".to_string()],
+                        ttl: None,
                     })
                 } else {
                     Response::NotFound
@@ -172,12 +256,17 @@ impl HostHooks for HardcodedHost {
         }
     }
 
-    fn get_host_by_name(name: &str, family: AddressFamily) -> Response<Host> {
-        if name.ends_with(".example") && family == AddressFamily::IPv4 {
+    fn get_host_by_name(name: &str, _family: AddressFamily) -> Response<Host> {
+        // Both families are returned, libnss only passes on those the caller asked for
+        if name.ends_with(".example") {
             Response::Success(Host {
                 name: name.to_string(),
-                addresses: Addresses::V4(vec![Ipv4Addr::new(177, 42, 42, 42)]),
+                addresses: Addresses::Mixed(vec![
+                    IpAddr::V4(Ipv4Addr::new(177, 42, 42, 42)),
+                    IpAddr::V6(Ipv6Addr::new(0xfd42, 0, 0, 0, 0, 0, 0, 0x42)),
+                ]),
                 aliases: vec!["test.example".to_string(), "other.example".to_string()],
+                ttl: Some(300),
             })
         } else {
             Response::NotFound
@@ -185,31 +274,275 @@ impl HostHooks for HardcodedHost {
     }
 }
 
-struct HardcodedInitgroups;
-libnss_initgroups_hooks!(hardcoded, HardcodedInitgroups);
+impl InitgroupsBackend for HardcodedBackend {
+    fn get_entries_by_user(&self, _user: String) -> Response<Vec<Group>> {
+        Response::Success(self.initgroups.clone())
+    }
+}
 
-impl InitgroupsHooks for HardcodedInitgroups {
-    fn get_entries_by_user(user: String) -> Response<Vec<Group>> {
-        let _ = user;
-        Response::Success(vec![
-            Group {
-                name: "initgroup1".to_string(),
-                passwd: "".to_string(),
-                gid: 3005,
-                members: vec!["someone".to_string()],
-            },
-            Group {
-                name: "initgroup2".to_string(),
-                passwd: "".to_string(),
-                gid: 3006,
-                members: vec!["someone".to_string()],
-            },
-            Group {
-                name: "initgroup3".to_string(),
-                passwd: "".to_string(),
-                gid: 3007,
-                members: vec!["someone".to_string()],
-            },
-        ])
+struct HardcodedService;
+libnss_service_hooks!(hardcoded, HardcodedService);
+
+impl ServiceHooks for HardcodedService {
+    fn get_all_entries() -> Response<Vec<Service>> {
+        Response::Success(vec![Service {
+            name: "test".to_string(),
+            aliases: vec!["test-alias".to_string()],
+            port: 4242,
+            proto: "tcp".to_string(),
+        }])
+    }
+
+    fn get_entry_by_name(name: String, proto: Option<String>) -> Response<Service> {
+        if name == "test" && proto.as_deref().unwrap_or("tcp") == "tcp" {
+            return Response::Success(Service {
+                name: "test".to_string(),
+                aliases: vec!["test-alias".to_string()],
+                port: 4242,
+                proto: "tcp".to_string(),
+            });
+        }
+
+        Response::NotFound
+    }
+
+    fn get_entry_by_port(port: u16, proto: Option<String>) -> Response<Service> {
+        if port == 4242 && proto.as_deref().unwrap_or("tcp") == "tcp" {
+            return Response::Success(Service {
+                name: "test".to_string(),
+                aliases: vec!["test-alias".to_string()],
+                port: 4242,
+                proto: "tcp".to_string(),
+            });
+        }
+
+        Response::NotFound
+    }
+}
+
+struct HardcodedProtocol;
+libnss_protocol_hooks!(hardcoded, HardcodedProtocol);
+
+impl ProtocolHooks for HardcodedProtocol {
+    fn get_all_entries() -> Response<Vec<Protocol>> {
+        Response::Success(vec![Protocol {
+            name: "test".to_string(),
+            aliases: vec!["TEST".to_string()],
+            number: 253,
+        }])
+    }
+
+    fn get_entry_by_name(name: String) -> Response<Protocol> {
+        if name == "test" {
+            return Response::Success(Protocol {
+                name: "test".to_string(),
+                aliases: vec!["TEST".to_string()],
+                number: 253,
+            });
+        }
+
+        Response::NotFound
+    }
+
+    fn get_entry_by_number(number: i32) -> Response<Protocol> {
+        if number == 253 {
+            return Response::Success(Protocol {
+                name: "test".to_string(),
+                aliases: vec!["TEST".to_string()],
+                number: 253,
+            });
+        }
+
+        Response::NotFound
+    }
+}
+
+struct HardcodedNetwork;
+libnss_network_hooks!(hardcoded, HardcodedNetwork);
+
+impl NetworkHooks for HardcodedNetwork {
+    fn get_all_entries() -> Response<Vec<Network>> {
+        Response::Success(vec![Network {
+            name: "testnet".to_string(),
+            aliases: vec!["testnet-alias".to_string()],
+            net: u32::from(Ipv4Addr::new(177, 42, 0, 0)),
+        }])
+    }
+
+    fn get_entry_by_name(name: String) -> Response<Network> {
+        if name == "testnet" {
+            return Response::Success(Network {
+                name: "testnet".to_string(),
+                aliases: vec!["testnet-alias".to_string()],
+                net: u32::from(Ipv4Addr::new(177, 42, 0, 0)),
+            });
+        }
+
+        Response::NotFound
+    }
+
+    fn get_entry_by_addr(net: u32) -> Response<Network> {
+        if net == u32::from(Ipv4Addr::new(177, 42, 0, 0)) {
+            return Response::Success(Network {
+                name: "testnet".to_string(),
+                aliases: vec!["testnet-alias".to_string()],
+                net: u32::from(Ipv4Addr::new(177, 42, 0, 0)),
+            });
+        }
+
+        Response::NotFound
+    }
+}
+
+struct HardcodedEthers;
+libnss_ethers_hooks!(hardcoded, HardcodedEthers);
+
+impl EthersHooks for HardcodedEthers {
+    fn get_all_entries() -> Response<Vec<Ether>> {
+        Response::Success(vec![Ether {
+            name: "test.example".to_string(),
+            addr: [0x02, 0x00, 0x00, 0x42, 0x42, 0x42],
+        }])
+    }
+
+    fn get_entry_by_name(name: String) -> Response<Ether> {
+        if name == "test.example" {
+            return Response::Success(Ether {
+                name: "test.example".to_string(),
+                addr: [0x02, 0x00, 0x00, 0x42, 0x42, 0x42],
+            });
+        }
+
+        Response::NotFound
+    }
+
+    fn get_entry_by_addr(addr: [u8; 6]) -> Response<Ether> {
+        if addr == [0x02, 0x00, 0x00, 0x42, 0x42, 0x42] {
+            return Response::Success(Ether {
+                name: "test.example".to_string(),
+                addr: [0x02, 0x00, 0x00, 0x42, 0x42, 0x42],
+            });
+        }
+
+        Response::NotFound
+    }
+}
+
+struct HardcodedRpc;
+libnss_rpc_hooks!(hardcoded, HardcodedRpc);
+
+impl RpcHooks for HardcodedRpc {
+    fn get_all_entries() -> Response<Vec<Rpc>> {
+        Response::Success(vec![Rpc {
+            name: "testprog".to_string(),
+            aliases: vec!["testprog-alias".to_string()],
+            number: 424242,
+        }])
+    }
+
+    fn get_entry_by_name(name: String) -> Response<Rpc> {
+        if name == "testprog" {
+            return Response::Success(Rpc {
+                name: "testprog".to_string(),
+                aliases: vec!["testprog-alias".to_string()],
+                number: 424242,
+            });
+        }
+
+        Response::NotFound
+    }
+
+    fn get_entry_by_number(number: i32) -> Response<Rpc> {
+        if number == 424242 {
+            return Response::Success(Rpc {
+                name: "testprog".to_string(),
+                aliases: vec!["testprog-alias".to_string()],
+                number: 424242,
+            });
+        }
+
+        Response::NotFound
+    }
+}
+
+struct HardcodedNetgroup;
+libnss_netgroup_hooks!(hardcoded, HardcodedNetgroup);
+
+impl NetgroupHooks for HardcodedNetgroup {
+    fn get_entries_by_name(netgroup: String) -> Response<Vec<NetgroupEntry>> {
+        match netgroup.as_str() {
+            "testgroup" => Response::Success(vec![
+                NetgroupEntry::Triple(NetgroupTriple {
+                    host: Some("test.example".to_string()),
+                    user: Some("test".to_string()),
+                    domain: None,
+                }),
+                NetgroupEntry::Group("testgroup-nested".to_string()),
+            ]),
+            "testgroup-nested" => Response::Success(vec![NetgroupEntry::Triple(NetgroupTriple {
+                host: Some("other.example".to_string()),
+                user: None,
+                domain: Some("example".to_string()),
+            })]),
+            _ => Response::NotFound,
+        }
+    }
+}
+
+struct HardcodedAlias;
+libnss_alias_hooks!(hardcoded, HardcodedAlias);
+
+impl AliasHooks for HardcodedAlias {
+    fn get_all_entries() -> Response<Vec<Alias>> {
+        Response::Success(vec![Alias {
+            name: "test-alias".to_string(),
+            members: vec!["test".to_string(), "someone@other.example".to_string()],
+            local: true,
+        }])
+    }
+
+    fn get_entry_by_name(name: String) -> Response<Alias> {
+        if name == "test-alias" {
+            return Response::Success(Alias {
+                name: "test-alias".to_string(),
+                members: vec!["test".to_string(), "someone@other.example".to_string()],
+                local: true,
+            });
+        }
+
+        Response::NotFound
+    }
+}
+
+struct HardcodedSubid;
+libnss_subid_hooks!(hardcoded, HardcodedSubid);
+
+// Delegates 100000-165535 to "test" for both subuids and subgids
+impl SubidHooks for HardcodedSubid {
+    fn get_ranges_by_owner(owner: String, _id_type: SubidType) -> Response<Vec<SubidRange>> {
+        if owner == "test" {
+            return Response::Success(vec![SubidRange {
+                start: 100000,
+                count: 65536,
+            }]);
+        }
+
+        Response::NotFound
+    }
+
+    fn get_owners_by_id(id: u64, _id_type: SubidType) -> Response<Vec<libc::uid_t>> {
+        if (100000..165536).contains(&id) {
+            return Response::Success(vec![1005]);
+        }
+
+        Response::Success(vec![])
+    }
+
+    fn has_range(owner: String, range: SubidRange, _id_type: SubidType) -> Response<bool> {
+        if owner == "test" {
+            return Response::Success(range.start >= 100000 && range.start + range.count <= 165536);
+        }
+
+        Response::NotFound
     }
 }
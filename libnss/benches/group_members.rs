@@ -0,0 +1,55 @@
+//! Measures writing a `Group` with a large member list into buffers of the sizes glibc uses when
+//! retrying on `ERANGE`, with and without clearing the whole buffer first.
+//!
+//! Run with `cargo bench -p libnss`.
+use libnss::group::{CGroup, Group};
+use libnss::interop::{CBuffer, Response};
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+const ITERATIONS: u32 = 200;
+
+fn group(members: usize) -> Response<Group> {
+    Response::Success(Group {
+        name: "large".to_string(),
+        passwd: "x".to_string(),
+        gid: 1000,
+        members: (0..members).map(|i| format!("member{}", i)).collect(),
+    })
+}
+
+fn measure<F: FnMut()>(mut f: F) -> Duration {
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        f();
+    }
+    start.elapsed() / ITERATIONS
+}
+
+fn main() {
+    for &(members, buflen) in &[
+        (100, 16 * 1024),
+        (10_000, 256 * 1024),
+        (10_000, 1024 * 1024),
+        (50_000, 1024 * 1024),
+    ] {
+        let response = group(members);
+        let mut buf = vec![0 as libc::c_char; buflen];
+        let mut result = std::mem::MaybeUninit::<CGroup>::uninit();
+        let mut errno = 0;
+
+        let to_c = measure(|| unsafe {
+            black_box(response.to_c(result.as_mut_ptr(), buf.as_mut_ptr(), buflen, &mut errno));
+        });
+
+        let cleared = measure(|| unsafe {
+            CBuffer::new(buf.as_mut_ptr() as *mut libc::c_void, buflen).clear();
+            black_box(response.to_c(result.as_mut_ptr(), buf.as_mut_ptr(), buflen, &mut errno));
+        });
+
+        println!(
+            "{:>6} members, {:>7} byte buffer: to_c {:>10.1?}, memset + to_c {:>10.1?}",
+            members, buflen, to_c, cleared
+        );
+    }
+}
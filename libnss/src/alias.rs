@@ -0,0 +1,120 @@
+use crate::interop::{enumerate, CBuffer, Enumeration, EnumerationMode, Response, ToC, ToCError};
+
+#[derive(Clone)]
+pub struct Alias {
+    pub name: String,
+    pub members: Vec<String>,
+    pub local: bool,
+}
+
+impl ToC<CAliasent> for Alias {
+    unsafe fn to_c(&self, result: *mut CAliasent, buffer: &mut CBuffer) -> Result<(), ToCError> {
+        (*result).name = buffer.write_str(&self.name)?;
+        (*result).members_len = self.members.len() as libc::size_t;
+        (*result).members = buffer.write_strs(&self.members)?;
+        (*result).local = self.local as libc::c_int;
+        Ok(())
+    }
+}
+
+pub trait AliasHooks {
+    fn get_all_entries() -> Response<Vec<Alias>>;
+
+    /// See `Enumeration`
+    fn open_enumeration() -> Response<Enumeration<Alias>> {
+        Self::get_all_entries().map(enumerate)
+    }
+
+    /// See `EnumerationMode`
+    fn enumeration_mode() -> EnumerationMode {
+        EnumerationMode::Process
+    }
+
+    fn get_entry_by_name(name: String) -> Response<Alias>;
+}
+
+/// NSS C Alias object
+/// https://man7.org/linux/man-pages/man3/getaliasent.3.html
+#[repr(C)]
+#[allow(missing_copy_implementations)]
+pub struct CAliasent {
+    pub name: *mut libc::c_char,
+    pub members_len: libc::size_t,
+    pub members: *mut *mut libc::c_char,
+    pub local: libc::c_int,
+}
+
+#[macro_export]
+macro_rules! libnss_alias_hooks {
+($mod_ident:ident, $hooks_ident:ident) => (
+    $crate::_macro_internal::paste! {
+        pub use self::[<libnss_alias_ $mod_ident _hooks_impl>]::*;
+        mod [<libnss_alias_ $mod_ident _hooks_impl>] {
+            #![allow(non_upper_case_globals)]
+
+            use libc::c_int;
+            use std::cell::RefCell;
+            use std::ffi::CStr;
+            use std::str;
+            use $crate::interop::{CBuffer, EnumerationCursor, EnumerationState, Response, NssStatus};
+            use $crate::alias::{CAliasent, Alias, AliasHooks};
+
+            thread_local! {
+            static [<ALIAS_ $mod_ident _THREAD_ITERATOR>]: RefCell<EnumerationCursor<Alias>> = RefCell::new(EnumerationCursor::new());
+            }
+
+            $crate::_macro_internal::lazy_static! {
+            static ref [<ALIAS_ $mod_ident _ITERATOR>]: EnumerationState<Alias> = EnumerationState::new(
+                <super::$hooks_ident as AliasHooks>::enumeration_mode(),
+                <super::$hooks_ident as AliasHooks>::open_enumeration,
+                &[<ALIAS_ $mod_ident _THREAD_ITERATOR>]
+            );
+            }
+
+            #[no_mangle]
+            extern "C" fn [<_nss_ $mod_ident _setaliasent>]() -> c_int {
+                $crate::interop::catch_panic_or(|| {
+                    [<ALIAS_ $mod_ident _ITERATOR>].set() as c_int
+                }, || NssStatus::Unavail as c_int)
+            }
+
+            #[no_mangle]
+            extern "C" fn [<_nss_ $mod_ident _endaliasent>]() -> c_int {
+                $crate::interop::catch_panic_or(|| {
+                    [<ALIAS_ $mod_ident _ITERATOR>].end() as c_int
+                }, || NssStatus::Unavail as c_int)
+            }
+
+            #[no_mangle]
+            unsafe extern "C" fn [<_nss_ $mod_ident _getaliasent_r>](
+                result: *mut CAliasent,
+                buf: *mut libc::c_char,
+                buflen: libc::size_t,
+                errnop: *mut c_int
+            ) -> c_int {
+                $crate::interop::catch_panic(errnop, || {
+                    [<ALIAS_ $mod_ident _ITERATOR>].get(errnop, |response| response.to_c(result, buf, buflen, errnop)) as c_int
+                })
+            }
+
+            #[no_mangle]
+            unsafe extern "C" fn [<_nss_ $mod_ident _getaliasbyname_r>](
+                name_: *const libc::c_char,
+                result: *mut CAliasent,
+                buf: *mut libc::c_char,
+                buflen: libc::size_t,
+                errnop: *mut c_int
+            ) -> c_int {
+                $crate::interop::catch_panic(errnop, || {
+                    let cstr = CStr::from_ptr(name_);
+
+                    match str::from_utf8(cstr.to_bytes()) {
+                        Ok(name) => <super::$hooks_ident as AliasHooks>::get_entry_by_name(name.to_string()),
+                        Err(_) => Response::NotFound
+                    }.to_c(result, buf, buflen, errnop) as c_int
+                })
+            }
+        }
+    }
+)
+}
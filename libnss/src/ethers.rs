@@ -0,0 +1,130 @@
+use crate::interop::{enumerate, CBuffer, Enumeration, EnumerationMode, Response, ToC, ToCError};
+
+#[derive(Clone)]
+pub struct Ether {
+    pub name: String,
+    pub addr: [u8; 6],
+}
+
+impl ToC<CEtherent> for Ether {
+    unsafe fn to_c(&self, result: *mut CEtherent, buffer: &mut CBuffer) -> Result<(), ToCError> {
+        (*result).name = buffer.write_str(&self.name)?;
+        (*result).addr = self.addr;
+        Ok(())
+    }
+}
+
+pub trait EthersHooks {
+    fn get_all_entries() -> Response<Vec<Ether>>;
+
+    /// See `Enumeration`
+    fn open_enumeration() -> Response<Enumeration<Ether>> {
+        Self::get_all_entries().map(enumerate)
+    }
+
+    /// See `EnumerationMode`
+    fn enumeration_mode() -> EnumerationMode {
+        EnumerationMode::Process
+    }
+
+    fn get_entry_by_name(name: String) -> Response<Ether>;
+
+    fn get_entry_by_addr(addr: [u8; 6]) -> Response<Ether>;
+}
+
+/// NSS C Ethers object
+/// https://code.woboq.org/userspace/glibc/include/netinet/ether.h.html
+#[repr(C)]
+#[allow(missing_copy_implementations)]
+pub struct CEtherent {
+    pub name: *mut libc::c_char,
+    pub addr: [u8; 6],
+}
+
+#[macro_export]
+macro_rules! libnss_ethers_hooks {
+($mod_ident:ident, $hooks_ident:ident) => (
+    $crate::_macro_internal::paste! {
+        pub use self::[<libnss_ethers_ $mod_ident _hooks_impl>]::*;
+        mod [<libnss_ethers_ $mod_ident _hooks_impl>] {
+            #![allow(non_upper_case_globals)]
+
+            use libc::c_int;
+            use std::cell::RefCell;
+            use std::ffi::CStr;
+            use std::str;
+            use $crate::interop::{CBuffer, EnumerationCursor, EnumerationState, Response, NssStatus};
+            use $crate::ethers::{CEtherent, Ether, EthersHooks};
+
+            thread_local! {
+            static [<ETHERS_ $mod_ident _THREAD_ITERATOR>]: RefCell<EnumerationCursor<Ether>> = RefCell::new(EnumerationCursor::new());
+            }
+
+            $crate::_macro_internal::lazy_static! {
+            static ref [<ETHERS_ $mod_ident _ITERATOR>]: EnumerationState<Ether> = EnumerationState::new(
+                <super::$hooks_ident as EthersHooks>::enumeration_mode(),
+                <super::$hooks_ident as EthersHooks>::open_enumeration,
+                &[<ETHERS_ $mod_ident _THREAD_ITERATOR>]
+            );
+            }
+
+            #[no_mangle]
+            extern "C" fn [<_nss_ $mod_ident _setetherent>]() -> c_int {
+                $crate::interop::catch_panic_or(|| {
+                    [<ETHERS_ $mod_ident _ITERATOR>].set() as c_int
+                }, || NssStatus::Unavail as c_int)
+            }
+
+            #[no_mangle]
+            extern "C" fn [<_nss_ $mod_ident _endetherent>]() -> c_int {
+                $crate::interop::catch_panic_or(|| {
+                    [<ETHERS_ $mod_ident _ITERATOR>].end() as c_int
+                }, || NssStatus::Unavail as c_int)
+            }
+
+            #[no_mangle]
+            unsafe extern "C" fn [<_nss_ $mod_ident _getetherent_r>](
+                result: *mut CEtherent,
+                buf: *mut libc::c_char,
+                buflen: libc::size_t,
+                errnop: *mut c_int
+            ) -> c_int {
+                $crate::interop::catch_panic(errnop, || {
+                    [<ETHERS_ $mod_ident _ITERATOR>].get(errnop, |response| response.to_c(result, buf, buflen, errnop)) as c_int
+                })
+            }
+
+            #[no_mangle]
+            unsafe extern "C" fn [<_nss_ $mod_ident _gethostton_r>](
+                name_: *const libc::c_char,
+                result: *mut CEtherent,
+                buf: *mut libc::c_char,
+                buflen: libc::size_t,
+                errnop: *mut c_int
+            ) -> c_int {
+                $crate::interop::catch_panic(errnop, || {
+                    let cstr = CStr::from_ptr(name_);
+
+                    match str::from_utf8(cstr.to_bytes()) {
+                        Ok(name) => <super::$hooks_ident as EthersHooks>::get_entry_by_name(name.to_string()),
+                        Err(_) => Response::NotFound
+                    }.to_c(result, buf, buflen, errnop) as c_int
+                })
+            }
+
+            #[no_mangle]
+            unsafe extern "C" fn [<_nss_ $mod_ident _getntohost_r>](
+                addr: *const [u8; 6],
+                result: *mut CEtherent,
+                buf: *mut libc::c_char,
+                buflen: libc::size_t,
+                errnop: *mut c_int
+            ) -> c_int {
+                $crate::interop::catch_panic(errnop, || {
+                    <super::$hooks_ident as EthersHooks>::get_entry_by_addr(*addr).to_c(result, buf, buflen, errnop) as c_int
+                })
+            }
+        }
+    }
+)
+}
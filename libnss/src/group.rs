@@ -1,4 +1,4 @@
-use crate::interop::{CBuffer, Response, ToC};
+use crate::interop::{enumerate, CBuffer, Enumeration, EnumerationMode, Response, ToC, ToCError};
 
 #[derive(Clone)]
 pub struct Group {
@@ -8,24 +8,66 @@ pub struct Group {
     pub members: Vec<String>,
 }
 
+impl Group {
+    /// Upper bound of the buffer space needed to return this entry
+    pub fn required_size(&self) -> usize {
+        CBuffer::str_size(&self.name)
+            + CBuffer::str_size(&self.passwd)
+            + CBuffer::strs_size(&self.members)
+    }
+}
+
 impl ToC<CGroup> for Group {
-    unsafe fn to_c(&self, result: *mut CGroup, buffer: &mut CBuffer) -> std::io::Result<()> {
+    unsafe fn to_c(&self, result: *mut CGroup, buffer: &mut CBuffer) -> Result<(), ToCError> {
         (*result).name = buffer.write_str(&self.name)?;
         (*result).passwd = buffer.write_str(&self.passwd)?;
         (*result).gid = self.gid as libc::gid_t;
         (*result).members = buffer.write_strs(&self.members)?;
         Ok(())
     }
+
+    fn required_size(&self) -> Option<usize> {
+        Some(Group::required_size(self))
+    }
 }
 
 pub trait GroupHooks {
     fn get_all_entries() -> Response<Vec<Group>>;
 
+    /// See `Enumeration`
+    fn open_enumeration() -> Response<Enumeration<Group>> {
+        Self::get_all_entries().map(enumerate)
+    }
+
+    /// See `EnumerationMode`
+    fn enumeration_mode() -> EnumerationMode {
+        EnumerationMode::Process
+    }
+
     fn get_entry_by_gid(gid: libc::gid_t) -> Response<Group>;
 
     fn get_entry_by_name(name: String) -> Response<Group>;
 }
 
+/// Instance based variant of `GroupHooks`, exported with `libnss_group_hooks!(name, Type = new)`
+pub trait GroupBackend {
+    fn get_all_entries(&self) -> Response<Vec<Group>>;
+
+    /// See `Enumeration`
+    fn open_enumeration(&self) -> Response<Enumeration<Group>> {
+        self.get_all_entries().map(enumerate)
+    }
+
+    /// See `EnumerationMode`
+    fn enumeration_mode(&self) -> EnumerationMode {
+        EnumerationMode::Process
+    }
+
+    fn get_entry_by_gid(&self, gid: libc::gid_t) -> Response<Group>;
+
+    fn get_entry_by_name(&self, name: String) -> Response<Group>;
+}
+
 #[repr(C)]
 #[allow(missing_copy_implementations)]
 pub struct CGroup {
@@ -44,30 +86,36 @@ macro_rules! libnss_group_hooks {
             #![allow(non_upper_case_globals)]
 
             use libc::c_int;
+            use std::cell::RefCell;
             use std::ffi::CStr;
             use std::str;
-            use std::sync::{Mutex, MutexGuard};
-            use $crate::interop::{CBuffer, Iterator, Response, NssStatus};
+            use $crate::interop::{CBuffer, EnumerationCursor, EnumerationState, Response, NssStatus};
             use $crate::group::{CGroup, GroupHooks, Group};
 
+            thread_local! {
+            static [<GROUP_ $mod_ident _THREAD_ITERATOR>]: RefCell<EnumerationCursor<Group>> = RefCell::new(EnumerationCursor::new());
+            }
+
             $crate::_macro_internal::lazy_static! {
-            static ref [<GROUP_ $mod_ident _ITERATOR>]: Mutex<Iterator<Group>> = Mutex::new(Iterator::<Group>::new());
+            static ref [<GROUP_ $mod_ident _ITERATOR>]: EnumerationState<Group> = EnumerationState::new(
+                <super::$hooks_ident as GroupHooks>::enumeration_mode(),
+                <super::$hooks_ident as GroupHooks>::open_enumeration,
+                &[<GROUP_ $mod_ident _THREAD_ITERATOR>]
+            );
             }
 
             #[no_mangle]
             extern "C" fn [<_nss_ $mod_ident _setgrent>]() -> c_int {
-                let mut iter: MutexGuard<Iterator<Group>> = [<GROUP_ $mod_ident _ITERATOR>].lock().unwrap();
-                let status = match(<super::$hooks_ident as GroupHooks>::get_all_entries()) {
-                    Response::Success(records) => iter.open(records),
-                    response => response.to_status(),
-                };
-                status as c_int
+                $crate::interop::catch_panic_or(|| {
+                    [<GROUP_ $mod_ident _ITERATOR>].set() as c_int
+                }, || NssStatus::Unavail as c_int)
             }
 
             #[no_mangle]
             extern "C" fn [<_nss_ $mod_ident _endgrent>]() -> c_int {
-                let mut iter: MutexGuard<Iterator<Group>> = [<GROUP_ $mod_ident _ITERATOR>].lock().unwrap();
-                iter.close() as c_int
+                $crate::interop::catch_panic_or(|| {
+                    [<GROUP_ $mod_ident _ITERATOR>].end() as c_int
+                }, || NssStatus::Unavail as c_int)
             }
 
             #[no_mangle]
@@ -77,12 +125,9 @@ macro_rules! libnss_group_hooks {
                 buflen: libc::size_t,
                 errnop: *mut c_int
             ) -> c_int {
-                let mut iter: MutexGuard<Iterator<Group>> = [<GROUP_ $mod_ident _ITERATOR>].lock().unwrap();
-                let code: c_int = iter.next().to_c(result, buf, buflen, errnop) as c_int;
-                if code == NssStatus::TryAgain as c_int {
-                    iter.previous();
-                }
-                return code;
+                $crate::interop::catch_panic(errnop, || {
+                    [<GROUP_ $mod_ident _ITERATOR>].get(errnop, |response| response.to_c(result, buf, buflen, errnop)) as c_int
+                })
             }
 
             #[no_mangle]
@@ -93,12 +138,14 @@ macro_rules! libnss_group_hooks {
                 buflen: libc::size_t,
                 errnop: *mut c_int
             ) -> c_int {
-                <super::$hooks_ident as GroupHooks>::get_entry_by_gid(uid).to_c(
-                    result,
-                    buf,
-                    buflen,
-                    errnop
-                ) as c_int
+                $crate::interop::catch_panic(errnop, || {
+                    <super::$hooks_ident as GroupHooks>::get_entry_by_gid(uid).to_c(
+                        result,
+                        buf,
+                        buflen,
+                        errnop
+                    ) as c_int
+                })
             }
 
             #[no_mangle]
@@ -109,14 +156,50 @@ macro_rules! libnss_group_hooks {
                 buflen: libc::size_t,
                 errnop: *mut c_int
             ) -> c_int {
-                let cstr = CStr::from_ptr(name_);
+                $crate::interop::catch_panic(errnop, || {
+                    let cstr = CStr::from_ptr(name_);
 
-                match str::from_utf8(cstr.to_bytes()) {
-                    Ok(name) => <super::$hooks_ident as GroupHooks>::get_entry_by_name(name.to_string()),
-                    Err(_) => Response::NotFound
-                }.to_c(result, buf, buflen, errnop) as c_int
+                    match str::from_utf8(cstr.to_bytes()) {
+                        Ok(name) => <super::$hooks_ident as GroupHooks>::get_entry_by_name(name.to_string()),
+                        Err(_) => Response::NotFound
+                    }.to_c(result, buf, buflen, errnop) as c_int
+                })
             }
         }
     }
-)
+);
+($mod_ident:ident, $backend:ty = $new:expr) => (
+    $crate::_macro_internal::paste! {
+        $crate::_macro_internal::lazy_static! {
+        static ref [<GROUP_ $mod_ident _INSTANCE>]: &'static $backend = $crate::interop::shared_instance(|| $new);
+        }
+
+        #[allow(non_camel_case_types)]
+        struct [<libnss_group_ $mod_ident _instance>];
+
+        impl $crate::group::GroupHooks for [<libnss_group_ $mod_ident _instance>] {
+            fn get_all_entries() -> $crate::interop::Response<Vec<$crate::group::Group>> {
+                <$backend as $crate::group::GroupBackend>::get_all_entries(*[<GROUP_ $mod_ident _INSTANCE>])
+            }
+
+            fn open_enumeration() -> $crate::interop::Response<$crate::interop::Enumeration<$crate::group::Group>> {
+                <$backend as $crate::group::GroupBackend>::open_enumeration(*[<GROUP_ $mod_ident _INSTANCE>])
+            }
+
+            fn enumeration_mode() -> $crate::interop::EnumerationMode {
+                <$backend as $crate::group::GroupBackend>::enumeration_mode(*[<GROUP_ $mod_ident _INSTANCE>])
+            }
+
+            fn get_entry_by_gid(gid: libc::gid_t) -> $crate::interop::Response<$crate::group::Group> {
+                <$backend as $crate::group::GroupBackend>::get_entry_by_gid(*[<GROUP_ $mod_ident _INSTANCE>], gid)
+            }
+
+            fn get_entry_by_name(name: String) -> $crate::interop::Response<$crate::group::Group> {
+                <$backend as $crate::group::GroupBackend>::get_entry_by_name(*[<GROUP_ $mod_ident _INSTANCE>], name)
+            }
+        }
+
+        $crate::libnss_group_hooks!($mod_ident, [<libnss_group_ $mod_ident _instance>]);
+    }
+);
 }
@@ -0,0 +1,119 @@
+use crate::interop::{enumerate, CBuffer, Enumeration, EnumerationMode, Response, ToC, ToCError};
+
+#[derive(Clone)]
+pub struct GShadow {
+    pub name: String,
+    pub passwd: String,
+    pub admins: Vec<String>,
+    pub members: Vec<String>,
+}
+
+impl ToC<CSgrp> for GShadow {
+    unsafe fn to_c(&self, result: *mut CSgrp, buffer: &mut CBuffer) -> Result<(), ToCError> {
+        (*result).name = buffer.write_str(&self.name)?;
+        (*result).passwd = buffer.write_str(&self.passwd)?;
+        (*result).admins = buffer.write_strs(&self.admins)?;
+        (*result).members = buffer.write_strs(&self.members)?;
+        Ok(())
+    }
+}
+
+pub trait GShadowHooks {
+    fn get_all_entries() -> Response<Vec<GShadow>>;
+
+    /// See `Enumeration`
+    fn open_enumeration() -> Response<Enumeration<GShadow>> {
+        Self::get_all_entries().map(enumerate)
+    }
+
+    /// See `EnumerationMode`
+    fn enumeration_mode() -> EnumerationMode {
+        EnumerationMode::Process
+    }
+
+    fn get_entry_by_name(name: String) -> Response<GShadow>;
+}
+
+#[repr(C)]
+#[allow(missing_copy_implementations)]
+pub struct CSgrp {
+    pub name: *mut libc::c_char,
+    pub passwd: *mut libc::c_char,
+    pub admins: *mut *mut libc::c_char,
+    pub members: *mut *mut libc::c_char,
+}
+
+#[macro_export]
+macro_rules! libnss_gshadow_hooks {
+($mod_ident:ident, $hooks_ident:ident) => (
+    $crate::_macro_internal::paste! {
+        pub use self::[<libnss_gshadow_ $mod_ident _hooks_impl>]::*;
+        mod [<libnss_gshadow_ $mod_ident _hooks_impl>] {
+            #![allow(non_upper_case_globals)]
+
+            use libc::c_int;
+            use std::cell::RefCell;
+            use std::ffi::CStr;
+            use std::str;
+            use $crate::interop::{CBuffer, EnumerationCursor, EnumerationState, Response, NssStatus};
+            use $crate::gshadow::{CSgrp, GShadowHooks, GShadow};
+
+            thread_local! {
+            static [<GSHADOW_ $mod_ident _THREAD_ITERATOR>]: RefCell<EnumerationCursor<GShadow>> = RefCell::new(EnumerationCursor::new());
+            }
+
+            $crate::_macro_internal::lazy_static! {
+            static ref [<GSHADOW_ $mod_ident _ITERATOR>]: EnumerationState<GShadow> = EnumerationState::new(
+                <super::$hooks_ident as GShadowHooks>::enumeration_mode(),
+                <super::$hooks_ident as GShadowHooks>::open_enumeration,
+                &[<GSHADOW_ $mod_ident _THREAD_ITERATOR>]
+            );
+            }
+
+            #[no_mangle]
+            extern "C" fn [<_nss_ $mod_ident _setsgent>]() -> c_int {
+                $crate::interop::catch_panic_or(|| {
+                    [<GSHADOW_ $mod_ident _ITERATOR>].set() as c_int
+                }, || NssStatus::Unavail as c_int)
+            }
+
+            #[no_mangle]
+            extern "C" fn [<_nss_ $mod_ident _endsgent>]() -> c_int {
+                $crate::interop::catch_panic_or(|| {
+                    [<GSHADOW_ $mod_ident _ITERATOR>].end() as c_int
+                }, || NssStatus::Unavail as c_int)
+            }
+
+            #[no_mangle]
+            unsafe extern "C" fn [<_nss_ $mod_ident _getsgent_r>](
+                result: *mut CSgrp,
+                buf: *mut libc::c_char,
+                buflen: libc::size_t,
+                errnop: *mut c_int
+            ) -> c_int {
+                $crate::interop::catch_panic(errnop, || {
+                    [<GSHADOW_ $mod_ident _ITERATOR>].get(errnop, |response| response.to_c(result, buf, buflen, errnop)) as c_int
+                })
+            }
+
+            #[no_mangle]
+            unsafe extern "C" fn [<_nss_ $mod_ident _getsgnam_r>](
+                name_: *const libc::c_char,
+                result: *mut CSgrp,
+                buf: *mut libc::c_char,
+                buflen: libc::size_t,
+                errnop: *mut c_int
+            ) -> c_int {
+                $crate::interop::catch_panic(errnop, || {
+                    let cstr = CStr::from_ptr(name_);
+
+                    match str::from_utf8(cstr.to_bytes()) {
+                        Ok(name) => <super::$hooks_ident as GShadowHooks>::get_entry_by_name(name.to_string()),
+                        Err(_) => Response::NotFound
+                    }.to_c(result, buf, buflen, errnop) as c_int
+                })
+            }
+        }
+    }
+)
+}
@@ -1,14 +1,16 @@
-use crate::interop::{CBuffer, Response, ToC};
-use std::mem;
+use crate::interop::{enumerate, CBuffer, CWriter, Enumeration, EnumerationMode, Response, ToC, ToCError};
 use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
 #[derive(Clone, Debug, PartialEq)]
 pub struct Host {
+    /// Official (canonical) name of the host, reported to callers of `gethostbyname3_r`
     pub name: String,
     pub aliases: Vec<String>,
     pub addresses: Addresses,
+    /// Time to live in seconds, reported as 0 if `None`
+    pub ttl: Option<u32>,
 }
 
-#[derive(PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum AddressFamily {
     IPv4,
     IPv6,
@@ -18,68 +20,203 @@ pub enum AddressFamily {
 pub enum Addresses {
     V4(Vec<Ipv4Addr>),
     V6(Vec<Ipv6Addr>),
+    /// Addresses of both families, split per requested family when returned to the caller
+    Mixed(Vec<IpAddr>),
+}
+
+impl Addresses {
+    pub fn len(&self) -> usize {
+        match self {
+            Addresses::V4(addrs) => addrs.len(),
+            Addresses::V6(addrs) => addrs.len(),
+            Addresses::Mixed(addrs) => addrs.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn to_ip_addrs(&self) -> Vec<IpAddr> {
+        match self {
+            Addresses::V4(addrs) => addrs.iter().map(|a| IpAddr::V4(*a)).collect(),
+            Addresses::V6(addrs) => addrs.iter().map(|a| IpAddr::V6(*a)).collect(),
+            Addresses::Mixed(addrs) => addrs.clone(),
+        }
+    }
+
+    /// Returns the addresses of a single family. `Unspecified` keeps single family addresses as
+    /// they are and prefers IPv4 for mixed addresses, falling back to IPv6 if there are none.
+    pub fn for_family(&self, family: AddressFamily) -> Addresses {
+        let addrs = self.to_ip_addrs();
+        let v4 = || {
+            addrs
+                .iter()
+                .filter_map(|a| match a {
+                    IpAddr::V4(a) => Some(*a),
+                    _ => None,
+                })
+                .collect::<Vec<Ipv4Addr>>()
+        };
+        let v6 = || {
+            addrs
+                .iter()
+                .filter_map(|a| match a {
+                    IpAddr::V6(a) => Some(*a),
+                    _ => None,
+                })
+                .collect::<Vec<Ipv6Addr>>()
+        };
+
+        match (family, self) {
+            (AddressFamily::IPv4, _) => Addresses::V4(v4()),
+            (AddressFamily::IPv6, _) => Addresses::V6(v6()),
+            (AddressFamily::Unspecified, Addresses::Mixed(_)) => match v4() {
+                v4 if v4.is_empty() => Addresses::V6(v6()),
+                v4 => Addresses::V4(v4),
+            },
+            (AddressFamily::Unspecified, addresses) => addresses.clone(),
+        }
+    }
+}
+
+impl Host {
+    /// Returns a copy of the host with only the addresses of the requested family, or `None` if
+    /// it has none
+    pub fn for_family(&self, family: AddressFamily) -> Option<Host> {
+        let addresses = self.addresses.for_family(family);
+        if addresses.is_empty() {
+            return None;
+        }
+
+        Some(Host {
+            name: self.name.clone(),
+            aliases: self.aliases.clone(),
+            addresses,
+            ttl: self.ttl,
+        })
+    }
+
+    /// Upper bound of the buffer space needed to return this entry as a `hostent`
+    pub fn required_size(&self) -> usize {
+        let addresses = self.addresses.for_family(AddressFamily::Unspecified);
+        let length = match addresses {
+            Addresses::V6(_) => 16,
+            _ => 4,
+        };
+
+        CBuffer::str_size(&self.name)
+            + CBuffer::strs_size(&self.aliases)
+            + CBuffer::aligned_size::<*mut libc::c_char>(addresses.len() + 1)
+            + addresses.len() * length
+    }
+}
+
+/// Narrows a successful response to a single family, or `NotFound` if no addresses remain
+pub fn filter_family(response: Response<Host>, family: AddressFamily) -> Response<Host> {
+    match response {
+        Response::Success(host) => match host.for_family(family) {
+            Some(host) => Response::Success(host),
+            None => Response::NotFound,
+        },
+        response => response,
+    }
 }
 
 impl ToC<CHost> for Host {
-    unsafe fn to_c(&self, hostent: *mut CHost, buffer: &mut CBuffer) -> std::io::Result<()> {
-        (*hostent).name = buffer.write_str(&self.name)?;
-        (*hostent).h_aliases = buffer.write_strs(&self.aliases[..])?;
+    unsafe fn to_c(&self, hostent: *mut CHost, buffer: &mut CBuffer) -> Result<(), ToCError> {
+        let mut writer = CWriter::new(buffer);
+        (*hostent).name = writer.str(&self.name)?.as_ptr();
+        (*hostent).h_aliases = writer.strs(&self.aliases)?.as_ptr();
 
-        let (addr_len, count) = match &self.addresses {
-            Addresses::V4(addrs) => {
-                (*hostent).h_addrtype = libc::AF_INET;
-                (*hostent).h_length = 4;
+        // A hostent can only hold a single family
+        let addresses = self.addresses.for_family(AddressFamily::Unspecified);
 
-                (4, addrs.len())
-            }
-            Addresses::V6(addrs) => {
+        match &addresses {
+            Addresses::V6(_) => {
                 (*hostent).h_addrtype = libc::AF_INET6;
                 (*hostent).h_length = 16;
-
-                (16, addrs.len())
             }
-        };
-
-        let ptr_size = mem::size_of::<*mut libc::c_char>() as isize;
-        let mut array_pos =
-            buffer.reserve(ptr_size * (count as isize + 1))? as *mut *mut libc::c_char;
-        (*hostent).h_addr_list = array_pos;
-
-        match &self.addresses {
-            Addresses::V4(addrs) => {
-                for a in addrs {
-                    let ptr = buffer.reserve(addr_len)?;
-
-                    let o = a.octets();
-                    libc::memcpy(
-                        ptr as *mut libc::c_void,
-                        o.as_ptr() as *mut libc::c_void,
-                        addr_len as usize,
-                    );
-
-                    array_pos.write(ptr);
-                    array_pos = array_pos.offset(1);
-                }
+            _ => {
+                (*hostent).h_addrtype = libc::AF_INET;
+                (*hostent).h_length = 4;
             }
-            Addresses::V6(addrs) => {
-                for a in addrs {
-                    let ptr = buffer.reserve(addr_len)?;
-
-                    let o = a.octets();
-                    libc::memcpy(
-                        ptr as *mut libc::c_void,
-                        o.as_ptr() as *mut libc::c_void,
-                        addr_len as usize,
-                    );
-
-                    array_pos.write(ptr);
-                    array_pos = array_pos.offset(1);
-                }
+        }
+
+        let octets = addresses
+            .to_ip_addrs()
+            .iter()
+            .map(octets)
+            .collect::<Vec<Vec<u8>>>();
+        (*hostent).h_addr_list = writer.blobs(&octets)?.as_ptr();
+
+        Ok(())
+    }
+
+    fn required_size(&self) -> Option<usize> {
+        Some(Host::required_size(self))
+    }
+}
+
+fn octets(addr: &IpAddr) -> Vec<u8> {
+    match addr {
+        IpAddr::V4(a) => a.octets().to_vec(),
+        IpAddr::V6(a) => a.octets().to_vec(),
+    }
+}
+
+impl ToC<*mut CGaihAddrtuple> for Vec<Host> {
+    unsafe fn to_c(
+        &self,
+        pat: *mut *mut CGaihAddrtuple,
+        buffer: &mut CBuffer,
+    ) -> Result<(), ToCError> {
+        let mut writer = CWriter::new(buffer);
+
+        let mut entries = vec![];
+        for host in self {
+            let name = writer.str(&host.name)?;
+            for a in host.addresses.to_ip_addrs() {
+                entries.push((name, a));
             }
         }
 
-        // Write null termination
-        libc::memset(array_pos as *mut libc::c_void, 0, 1);
+        // Write the list back to front so each tuple can point to the next
+        let mut next: *mut CGaihAddrtuple = std::ptr::null_mut();
+        for (name, a) in entries.iter().rev() {
+            let mut addr = [0u8; 16];
+            let o = octets(a);
+            addr[..o.len()].copy_from_slice(&o);
+
+            let tuple = writer.value(CGaihAddrtuple {
+                next,
+                name: name.as_ptr(),
+                family: match a {
+                    IpAddr::V4(_) => libc::AF_INET,
+                    IpAddr::V6(_) => libc::AF_INET6,
+                },
+                addr: [
+                    u32::from_ne_bytes([addr[0], addr[1], addr[2], addr[3]]),
+                    u32::from_ne_bytes([addr[4], addr[5], addr[6], addr[7]]),
+                    u32::from_ne_bytes([addr[8], addr[9], addr[10], addr[11]]),
+                    u32::from_ne_bytes([addr[12], addr[13], addr[14], addr[15]]),
+                ],
+                scopeid: 0,
+            })?;
+            next = tuple.as_ptr();
+        }
+
+        if next.is_null() {
+            return Ok(());
+        }
+
+        // The caller may provide storage for the first tuple
+        if (*pat).is_null() {
+            *pat = next;
+        } else {
+            **pat = next.read();
+        }
+
         Ok(())
     }
 }
@@ -87,9 +224,90 @@ impl ToC<CHost> for Host {
 pub trait HostHooks {
     fn get_all_entries() -> Response<Vec<Host>>;
 
+    /// See `Enumeration`
+    fn open_enumeration() -> Response<Enumeration<Host>> {
+        Self::get_all_entries().map(enumerate)
+    }
+
+    /// See `EnumerationMode`
+    fn enumeration_mode() -> EnumerationMode {
+        EnumerationMode::Process
+    }
+
     fn get_host_by_name(name: &str, family: AddressFamily) -> Response<Host>;
 
     fn get_host_by_addr(addr: IpAddr) -> Response<Host>;
+
+    /// Lookup the addresses of both families for a name, as used by `getaddrinfo`.
+    /// Defaults to combining the IPv4 and IPv6 results of `get_host_by_name`.
+    fn get_hosts_by_name(name: &str) -> Response<Vec<Host>> {
+        hosts_by_name(|family| Self::get_host_by_name(name, family))
+    }
+
+    /// Lookup the canonical name for a name, as used by `getaddrinfo` with `AI_CANONNAME`.
+    /// Defaults to the name of the host returned by `get_host_by_name`, trying IPv4 then IPv6.
+    fn get_canonical_name(name: &str) -> Response<String> {
+        canonical_name(|family| Self::get_host_by_name(name, family))
+    }
+}
+
+pub(crate) fn hosts_by_name<F: Fn(AddressFamily) -> Response<Host>>(
+    lookup: F,
+) -> Response<Vec<Host>> {
+    // Hosts with mixed addresses would otherwise be returned twice
+    let v4 = filter_family(lookup(AddressFamily::IPv4), AddressFamily::IPv4);
+    let v6 = filter_family(lookup(AddressFamily::IPv6), AddressFamily::IPv6);
+
+    match (v4, v6) {
+        (Response::Success(v4), Response::Success(v6)) => Response::Success(vec![v4, v6]),
+        (Response::Success(host), _) | (_, Response::Success(host)) => Response::Success(vec![host]),
+        (Response::NotFound, Response::NotFound) => Response::NotFound,
+        (Response::TryAgain, _) | (_, Response::TryAgain) => Response::TryAgain,
+        (Response::Unavail, _) | (_, Response::Unavail) => Response::Unavail,
+        _ => Response::Return,
+    }
+}
+
+pub(crate) fn canonical_name<F: Fn(AddressFamily) -> Response<Host>>(
+    lookup: F,
+) -> Response<String> {
+    let response = match lookup(AddressFamily::IPv4) {
+        Response::NotFound => lookup(AddressFamily::IPv6),
+        response => response,
+    };
+
+    response.map(|host| host.name)
+}
+
+/// Instance based variant of `HostHooks`, exported with `libnss_host_hooks!(name, Type = new)`
+pub trait HostBackend {
+    fn get_all_entries(&self) -> Response<Vec<Host>>;
+
+    /// See `Enumeration`
+    fn open_enumeration(&self) -> Response<Enumeration<Host>> {
+        self.get_all_entries().map(enumerate)
+    }
+
+    /// See `EnumerationMode`
+    fn enumeration_mode(&self) -> EnumerationMode {
+        EnumerationMode::Process
+    }
+
+    fn get_host_by_name(&self, name: &str, family: AddressFamily) -> Response<Host>;
+
+    fn get_host_by_addr(&self, addr: IpAddr) -> Response<Host>;
+
+    /// Lookup the addresses of both families for a name, as used by `getaddrinfo`.
+    /// Defaults to combining the IPv4 and IPv6 results of `get_host_by_name`.
+    fn get_hosts_by_name(&self, name: &str) -> Response<Vec<Host>> {
+        hosts_by_name(|family| self.get_host_by_name(name, family))
+    }
+
+    /// Lookup the canonical name for a name, as used by `getaddrinfo` with `AI_CANONNAME`.
+    /// Defaults to the name of the host returned by `get_host_by_name`, trying IPv4 then IPv6.
+    fn get_canonical_name(&self, name: &str) -> Response<String> {
+        canonical_name(|family| self.get_host_by_name(name, family))
+    }
 }
 
 /// NSS C Host object
@@ -105,6 +323,18 @@ pub struct CHost {
     pub h_addr_list: *mut *mut libc::c_char,
 }
 
+/// glibc's address list entry for `gethostbyname4_r`
+/// https://code.woboq.org/userspace/glibc/nss/nss.h.html
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct CGaihAddrtuple {
+    pub next: *mut CGaihAddrtuple,
+    pub name: *mut libc::c_char,
+    pub family: libc::c_int,
+    pub addr: [u32; 4],
+    pub scopeid: u32,
+}
+
 #[macro_export]
 macro_rules! libnss_host_hooks {
 ($mod_ident:ident, $hooks_ident:ident) => (
@@ -114,12 +344,12 @@ macro_rules! libnss_host_hooks {
             #![allow(non_upper_case_globals)]
 
             use libc::c_int;
+            use std::cell::RefCell;
             use std::ffi::CStr;
             use std::str;
-            use std::sync::{Mutex, MutexGuard};
             use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
-            use $crate::host::{CHost, HostHooks, Host, AddressFamily};
-            use $crate::interop::{CBuffer, Response, NssStatus, Iterator};
+            use $crate::host::{filter_family, Addresses, CGaihAddrtuple, CHost, HostHooks, Host, AddressFamily};
+            use $crate::interop::{CBuffer, EnumerationCursor, EnumerationState, Response, NssStatus};
 
             // https://code.woboq.org/userspace/glibc/resolv/netdb.h.html#62
             enum Herrno {
@@ -130,35 +360,56 @@ macro_rules! libnss_host_hooks {
                 NoData = 4,
             }
 
+            unsafe fn set_herrno(status: NssStatus, errnop: *mut c_int, h_errnop: *mut c_int) {
+                *h_errnop = match status {
+                    NssStatus::Success => Herrno::NetDbSuccess,
+                    // glibc only retries with a larger buffer when h_errno is NETDB_INTERNAL
+                    NssStatus::TryAgain if *errnop == libc::ERANGE => Herrno::NetDbInternal,
+                    NssStatus::TryAgain => Herrno::TryAgain,
+                    NssStatus::Unavail => Herrno::NoRecovery,
+                    NssStatus::NotFound => Herrno::NoData,
+                    _ => Herrno::NetDbInternal,
+                } as i32;
+            }
+
+            fn ttl_to_c(ttl: Option<u32>) -> i32 {
+                ttl.map_or(0, |ttl| ttl.min(i32::MAX as u32) as i32)
+            }
+
+            thread_local! {
+            static [<HOST_ $mod_ident _THREAD_ITERATOR>]: RefCell<EnumerationCursor<Host>> = RefCell::new(EnumerationCursor::new());
+            }
+
             $crate::_macro_internal::lazy_static! {
-            static ref [<HOST_ $mod_ident _ITERATOR>]: Mutex<Iterator<Host>> = Mutex::new(Iterator::<Host>::new());
+            static ref [<HOST_ $mod_ident _ITERATOR>]: EnumerationState<Host> = EnumerationState::new(
+                <super::$hooks_ident as HostHooks>::enumeration_mode(),
+                <super::$hooks_ident as HostHooks>::open_enumeration,
+                &[<HOST_ $mod_ident _THREAD_ITERATOR>]
+            );
             }
 
             #[no_mangle]
             extern "C" fn [<_nss_ $mod_ident _sethostent>]() -> c_int {
-                let mut iter: MutexGuard<Iterator<Host>> = [<HOST_ $mod_ident _ITERATOR>].lock().unwrap();
-                let status = match(<super::$hooks_ident as HostHooks>::get_all_entries()) {
-                    Response::Success(entries) => iter.open(entries),
-                    response => response.to_status()
-                };
-                status as c_int
+                $crate::interop::catch_panic_or(|| {
+                    [<HOST_ $mod_ident _ITERATOR>].set() as c_int
+                }, || NssStatus::Unavail as c_int)
             }
 
             #[no_mangle]
             extern "C" fn [<_nss_ $mod_ident _endhostent>]() -> c_int {
-                let mut iter: MutexGuard<Iterator<Host>> = [<HOST_ $mod_ident _ITERATOR>].lock().unwrap();
-                iter.close() as c_int
+                $crate::interop::catch_panic_or(|| {
+                    [<HOST_ $mod_ident _ITERATOR>].end() as c_int
+                }, || NssStatus::Unavail as c_int)
             }
 
             #[no_mangle]
             unsafe extern "C" fn [<_nss_ $mod_ident _gethostent_r>](result: *mut CHost, buf: *mut libc::c_char, buflen: libc::size_t,
-                                                                  errnop: *mut c_int) -> c_int {
-                let mut iter: MutexGuard<Iterator<Host>> = [<HOST_ $mod_ident _ITERATOR>].lock().unwrap();
-                let code: c_int = iter.next().to_c(result, buf, buflen, errnop) as c_int;
-                if code == NssStatus::TryAgain as c_int {
-                    iter.previous();
-                }
-                return code;
+                                                                  errnop: *mut c_int, h_errnop: *mut c_int) -> c_int {
+                $crate::interop::catch_panic_herrno(errnop, h_errnop, || {
+                    let status = [<HOST_ $mod_ident _ITERATOR>].get(errnop, |response| response.to_c(result, buf, buflen, errnop));
+                    set_herrno(status, errnop, h_errnop);
+                    status as c_int
+                })
             }
 
             #[no_mangle]
@@ -172,33 +423,44 @@ macro_rules! libnss_host_hooks {
                 errnop: *mut c_int,
                 h_errnop: *mut c_int
             ) -> c_int {
-                *h_errnop = Herrno::NetDbInternal as i32;
-
-                // Convert address type
-                let a = match (len, format) {
-                    (4, libc::AF_INET) => {
-                        let mut p = [0u8; 4];
-                        libc::memcpy(p.as_ptr() as *mut libc::c_void, addr as *mut libc::c_void, 4);
-                        IpAddr::V4(Ipv4Addr::from(p))
-                    },
-                    (16, libc::AF_INET6) => {
-                        let mut p = [0u8; 16];
-                        libc::memcpy(p.as_ptr() as *mut libc::c_void, addr as *mut libc::c_void, 16);
-                        IpAddr::V6(Ipv6Addr::from(p))
-                    },
-                    _ => {
-                        //error!("address length and format mismatch (length: {}, format: {})", len, format);
-                        return NssStatus::NotFound as c_int;
-                    }
-                };
-
-                match <super::$hooks_ident as HostHooks>::get_host_by_addr(a) {
-                    response @ Response::Success(..) => {
-                        *h_errnop = Herrno::NetDbSuccess as i32;
-                        response
-                    },
-                    response => response
-                }.to_c(result, buf, buflen, errnop) as c_int
+                $crate::interop::catch_panic_herrno(errnop, h_errnop, || {
+                    *h_errnop = Herrno::NetDbInternal as i32;
+
+                    // Convert address type
+                    let a = match (len, format) {
+                        (4, libc::AF_INET) => {
+                            let mut p = [0u8; 4];
+                            libc::memcpy(p.as_ptr() as *mut libc::c_void, addr as *mut libc::c_void, 4);
+                            IpAddr::V4(Ipv4Addr::from(p))
+                        },
+                        (16, libc::AF_INET6) => {
+                            let mut p = [0u8; 16];
+                            libc::memcpy(p.as_ptr() as *mut libc::c_void, addr as *mut libc::c_void, 16);
+                            IpAddr::V6(Ipv6Addr::from(p))
+                        },
+                        _ => {
+                            //error!("address length and format mismatch (length: {}, format: {})", len, format);
+                            *errnop = libc::ENOENT;
+                            set_herrno(NssStatus::NotFound, errnop, h_errnop);
+                            return NssStatus::NotFound as c_int;
+                        }
+                    };
+
+                    // The hostent holds the queried address, as with glibc's dns module, so it has the
+                    // queried family even if the backend only knows the name
+                    let response = <super::$hooks_ident as HostHooks>::get_host_by_addr(a).map(|host| Host {
+                        addresses: match a {
+                            IpAddr::V4(a) => Addresses::V4(vec![a]),
+                            IpAddr::V6(a) => Addresses::V6(vec![a]),
+                        },
+                        ..host
+                    });
+                    let status = response.to_c(result, buf, buflen, errnop);
+
+                    set_herrno(status, errnop, h_errnop);
+
+                    status as c_int
+                })
             }
 
             #[no_mangle]
@@ -210,32 +472,74 @@ macro_rules! libnss_host_hooks {
                 errnop: *mut libc::c_int,
                 h_errnop: *mut libc::c_int
             ) -> libc::c_int {
-                [<_nss_ $mod_ident _gethostbyname2_r>](name, libc::AF_UNSPEC, result, buf, buflen, errnop, h_errnop)
+                $crate::interop::catch_panic_herrno(errnop, h_errnop, || {
+                    [<_nss_ $mod_ident _gethostbyname2_r>](name, libc::AF_UNSPEC, result, buf, buflen, errnop, h_errnop)
+                })
             }
 
             #[no_mangle]
-            unsafe extern "C" fn [<_nss_ $mod_ident _gethostbyname3_r>](
+            unsafe extern "C" fn [<_nss_ $mod_ident _gethostbyname4_r>](
                 name: *const libc::c_char,
-                family: libc::c_int,
-                result: *mut CHost,
+                pat: *mut *mut CGaihAddrtuple,
                 buf: *mut libc::c_char,
                 buflen: libc::size_t,
                 errnop: *mut libc::c_int,
                 h_errnop: *mut libc::c_int,
-                ttlp: *mut i32,
-                canonp: *mut *const libc::c_char
+                ttlp: *mut i32
             ) -> libc::c_int {
-                let name2_res = [<_nss_ $mod_ident _gethostbyname2_r>](name, family, result, buf, buflen, errnop, h_errnop);
+                $crate::interop::catch_panic_herrno(errnop, h_errnop, || {
+                    let cstr = CStr::from_ptr(name);
+
+                    let response = match str::from_utf8(cstr.to_bytes()) {
+                        Ok(name) => match <super::$hooks_ident as HostHooks>::get_hosts_by_name(name) {
+                            Response::Success(hosts) if hosts.iter().all(|host| host.addresses.is_empty()) => Response::NotFound,
+                            response => response
+                        },
+                        Err(_) => Response::NotFound
+                    };
+
+                    // Report the shortest TTL of all returned hosts
+                    let ttl = match &response {
+                        Response::Success(hosts) => hosts.iter().filter_map(|host| host.ttl).min(),
+                        _ => None,
+                    };
 
-                if ! ttlp.is_null() {
-                    *ttlp = 0;
-                }
+                    let status = response.to_c(pat, buf, buflen, errnop);
 
-                if ! canonp.is_null() {
-                    *canonp = name;
-                }
+                    set_herrno(status, errnop, h_errnop);
+
+                    if status == NssStatus::Success && ! ttlp.is_null() {
+                        *ttlp = ttl_to_c(ttl);
+                    }
 
-                name2_res
+                    status as c_int
+                })
+            }
+
+            #[no_mangle]
+            unsafe extern "C" fn [<_nss_ $mod_ident _getcanonname_r>](
+                name: *const libc::c_char,
+                buf: *mut libc::c_char,
+                buflen: libc::size_t,
+                result: *mut *mut libc::c_char,
+                errnop: *mut libc::c_int,
+                h_errnop: *mut libc::c_int
+            ) -> libc::c_int {
+                $crate::interop::catch_panic_herrno(errnop, h_errnop, || {
+                    let cstr = CStr::from_ptr(name);
+
+                    let status = match str::from_utf8(cstr.to_bytes()) {
+                        Ok(name) => <super::$hooks_ident as HostHooks>::get_canonical_name(name).to_c(result, buf, buflen, errnop),
+                        Err(_) => {
+                            *errnop = libc::ENOENT;
+                            NssStatus::NotFound
+                        }
+                    };
+
+                    set_herrno(status, errnop, h_errnop);
+
+                    status as c_int
+                })
             }
 
             #[no_mangle]
@@ -248,55 +552,131 @@ macro_rules! libnss_host_hooks {
                 errnop: *mut libc::c_int,
                 h_errnop: *mut libc::c_int
             ) -> libc::c_int {
+                $crate::interop::catch_panic_herrno(errnop, h_errnop, || {
+                    [<_nss_ $mod_ident _gethostbyname3_r>](
+                        name,
+                        family,
+                        result,
+                        buf,
+                        buflen,
+                        errnop,
+                        h_errnop,
+                        std::ptr::null_mut(),
+                        std::ptr::null_mut()
+                    )
+                })
+            }
 
-                let cstr = CStr::from_ptr(name);
-
-                let status = match str::from_utf8(cstr.to_bytes()) {
-                    Ok(name) => {
-                        use super::$hooks_ident as hooks;
-                        let status = match family {
-                            libc::AF_INET => <hooks as HostHooks>::get_host_by_name(&name.to_string(), AddressFamily::IPv4),
-                            libc::AF_INET6 => <hooks as HostHooks>::get_host_by_name(&name.to_string(), AddressFamily::IPv6),
-
-                            // If unspecified, we are probably being called from gethostbyname_r so
-                            // we will try IPv4 and if no results, then try IPv6
-                            libc::AF_UNSPEC => match <hooks as HostHooks>::get_host_by_name(&name.to_string(), AddressFamily::IPv4) {
-                                Response::NotFound => <hooks as HostHooks>::get_host_by_name(&name.to_string(), AddressFamily::IPv6),
-                                val => val,
-                            },
-                            _ => {
-                                *h_errnop = Herrno::NoRecovery as i32;
-                                Response::Unavail
-                            },
-                        }.to_c(result, buf, buflen, errnop);
-
-                        match status {
-                            NssStatus::Success => {
-                                *h_errnop = Herrno::NetDbSuccess as i32
-                            }
-                            NssStatus::TryAgain => {
-                                *h_errnop = Herrno::TryAgain as i32
-                            }
-                            NssStatus::Unavail => {
-                                *h_errnop = Herrno::NoRecovery as i32
-                            }
-                            NssStatus::NotFound => {
-                                *h_errnop = Herrno::NoData as i32
-                            }
-                            _ => {
-                                *h_errnop = Herrno::NetDbInternal as i32
+            #[no_mangle]
+            unsafe extern "C" fn [<_nss_ $mod_ident _gethostbyname3_r>](
+                name: *const libc::c_char,
+                family: libc::c_int,
+                result: *mut CHost,
+                buf: *mut libc::c_char,
+                buflen: libc::size_t,
+                errnop: *mut libc::c_int,
+                h_errnop: *mut libc::c_int,
+                ttlp: *mut i32,
+                canonp: *mut *const libc::c_char
+            ) -> libc::c_int {
+                $crate::interop::catch_panic_herrno(errnop, h_errnop, || {
+                    let cstr = CStr::from_ptr(name);
+
+                    let status = match str::from_utf8(cstr.to_bytes()) {
+                        Ok(name) => {
+                            use super::$hooks_ident as hooks;
+
+                            // Only return the addresses of the requested family
+                            let lookup = |family: AddressFamily, filter: AddressFamily| {
+                                filter_family(<hooks as HostHooks>::get_host_by_name(&name.to_string(), family), filter)
+                            };
+
+                            let response = match family {
+                                libc::AF_INET => lookup(AddressFamily::IPv4, AddressFamily::IPv4),
+                                libc::AF_INET6 => lookup(AddressFamily::IPv6, AddressFamily::IPv6),
+
+                                // If unspecified, we are probably being called from gethostbyname_r so
+                                // we will try IPv4 and if no results, then try IPv6
+                                libc::AF_UNSPEC => match lookup(AddressFamily::IPv4, AddressFamily::Unspecified) {
+                                    Response::NotFound => lookup(AddressFamily::IPv6, AddressFamily::Unspecified),
+                                    val => val,
+                                },
+                                _ => Response::Unavail,
+                            };
+
+                            let ttl = match &response {
+                                Response::Success(host) => host.ttl,
+                                _ => None,
+                            };
+
+                            let status = response.to_c(result, buf, buflen, errnop);
+
+                            set_herrno(status, errnop, h_errnop);
+
+                            if status == NssStatus::Success {
+                                if ! ttlp.is_null() {
+                                    *ttlp = ttl_to_c(ttl);
+                                }
+
+                                // The official name has already been copied into the caller's buffer
+                                if ! canonp.is_null() {
+                                    *canonp = (*result).name;
+                                }
                             }
-                        };
 
-                        status
-                    }
+                            status
+                        }
 
-                    Err(_) => NssStatus::NotFound
-                };
+                        Err(_) => NssStatus::NotFound
+                    };
 
-                status as c_int
+                    status as c_int
+                })
             }
 
         }
     }
-)}
+);
+($mod_ident:ident, $backend:ty = $new:expr) => (
+    $crate::_macro_internal::paste! {
+        $crate::_macro_internal::lazy_static! {
+        static ref [<HOST_ $mod_ident _INSTANCE>]: &'static $backend = $crate::interop::shared_instance(|| $new);
+        }
+
+        #[allow(non_camel_case_types)]
+        struct [<libnss_host_ $mod_ident _instance>];
+
+        impl $crate::host::HostHooks for [<libnss_host_ $mod_ident _instance>] {
+            fn get_all_entries() -> $crate::interop::Response<Vec<$crate::host::Host>> {
+                <$backend as $crate::host::HostBackend>::get_all_entries(*[<HOST_ $mod_ident _INSTANCE>])
+            }
+
+            fn open_enumeration() -> $crate::interop::Response<$crate::interop::Enumeration<$crate::host::Host>> {
+                <$backend as $crate::host::HostBackend>::open_enumeration(*[<HOST_ $mod_ident _INSTANCE>])
+            }
+
+            fn enumeration_mode() -> $crate::interop::EnumerationMode {
+                <$backend as $crate::host::HostBackend>::enumeration_mode(*[<HOST_ $mod_ident _INSTANCE>])
+            }
+
+            fn get_host_by_name(name: &str, family: $crate::host::AddressFamily) -> $crate::interop::Response<$crate::host::Host> {
+                <$backend as $crate::host::HostBackend>::get_host_by_name(*[<HOST_ $mod_ident _INSTANCE>], name, family)
+            }
+
+            fn get_host_by_addr(addr: std::net::IpAddr) -> $crate::interop::Response<$crate::host::Host> {
+                <$backend as $crate::host::HostBackend>::get_host_by_addr(*[<HOST_ $mod_ident _INSTANCE>], addr)
+            }
+
+            fn get_hosts_by_name(name: &str) -> $crate::interop::Response<Vec<$crate::host::Host>> {
+                <$backend as $crate::host::HostBackend>::get_hosts_by_name(*[<HOST_ $mod_ident _INSTANCE>], name)
+            }
+
+            fn get_canonical_name(name: &str) -> $crate::interop::Response<String> {
+                <$backend as $crate::host::HostBackend>::get_canonical_name(*[<HOST_ $mod_ident _INSTANCE>], name)
+            }
+        }
+
+        $crate::libnss_host_hooks!($mod_ident, [<libnss_host_ $mod_ident _instance>]);
+    }
+);
+}
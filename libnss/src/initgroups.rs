@@ -5,6 +5,12 @@ pub trait InitgroupsHooks {
     fn get_entries_by_user(user: String) -> Response<Vec<Group>>;
 }
 
+/// Instance based variant of `InitgroupsHooks`, exported with
+/// `libnss_initgroups_hooks!(name, Type = new)`
+pub trait InitgroupsBackend {
+    fn get_entries_by_user(&self, user: String) -> Response<Vec<Group>>;
+}
+
 #[macro_export]
 macro_rules! libnss_initgroups_hooks {
 ($mod_ident:ident, $hooks_ident:ident) => (
@@ -31,52 +37,72 @@ macro_rules! libnss_initgroups_hooks {
                 limit: libc::size_t,
                 errnop: *mut c_int,
             ) -> c_int {
-                let user = match std::str::from_utf8(CStr::from_ptr(name).to_bytes()) {
-                    Ok(x) => x.to_owned(),
-                    Err(_) => {
-                        *errnop = ENOENT;
-                        return NssStatus::NotFound as c_int;
+                $crate::interop::catch_panic(errnop, || {
+                    let user = match std::str::from_utf8(CStr::from_ptr(name).to_bytes()) {
+                        Ok(x) => x.to_owned(),
+                        Err(_) => {
+                            *errnop = ENOENT;
+                            return NssStatus::NotFound as c_int;
+                        }
+                    };
+
+                    let groups: Vec<Group> = match <super::$hooks_ident as InitgroupsHooks>::get_entries_by_user(user) {
+                        Response::Success(records) => records,
+                        response => {
+                            *errnop = ENOENT;
+                            return response.to_status() as c_int;
+                        }
+                    };
+                    let groups = groups
+                        .into_iter()
+                        .filter_map(|x| {
+                            if x.gid == skipgroup {
+                                None
+                            } else {
+                                Some(x.gid as libc::gid_t)
+                            }
+                        })
+                        .take(limit - *start)
+                        .collect::<Vec<libc::gid_t>>();
+                    if groups.is_empty() {
+                        return NssStatus::Success as c_int;
                     }
-                };
 
-                let groups: Vec<Group> = match <super::$hooks_ident as InitgroupsHooks>::get_entries_by_user(user) {
-                    Response::Success(records) => records,
-                    response => {
-                        *errnop = ENOENT;
-                        return response.to_status() as c_int;
+                    if *start + groups.len() != *size {
+                        let new_size = *start + groups.len();
+                        *groupsp = libc::realloc(
+                            *groupsp as *mut libc::c_void,
+                            new_size * mem::size_of::<libc::gid_t>(),
+                        ) as *mut libc::gid_t;
+                        *size = new_size;
                     }
-                };
-                let groups = groups
-                    .into_iter()
-                    .filter_map(|x| {
-                        if x.gid == skipgroup {
-                            None
-                        } else {
-                            Some(x.gid as libc::gid_t)
-                        }
-                    })
-                    .take(limit - *start)
-                    .collect::<Vec<libc::gid_t>>();
-                if groups.is_empty() {
-                    return NssStatus::Success as c_int;
-                }
 
-                if *start + groups.len() != *size {
-                    let new_size = *start + groups.len();
-                    *groupsp = libc::realloc(
-                        *groupsp as *mut libc::c_void,
-                        new_size * mem::size_of::<libc::gid_t>(),
-                    ) as *mut libc::gid_t;
-                    *size = new_size;
-                }
+                    let group_array: &mut [libc::gid_t] = slice::from_raw_parts_mut(*groupsp, *size);
+                    group_array[*start..*size].copy_from_slice(&groups);
+                    *start = group_array.len();
 
-                let group_array: &mut [libc::gid_t] = slice::from_raw_parts_mut(*groupsp, *size);
-                group_array[*start..*size].copy_from_slice(&groups);
-                *start = group_array.len();
+                    NssStatus::Success as i32
+                })
+            }
+        }
+    }
+);
+($mod_ident:ident, $backend:ty = $new:expr) => (
+    $crate::_macro_internal::paste! {
+        $crate::_macro_internal::lazy_static! {
+        static ref [<INITGROUPS_ $mod_ident _INSTANCE>]: &'static $backend = $crate::interop::shared_instance(|| $new);
+        }
+
+        #[allow(non_camel_case_types)]
+        struct [<libnss_initgroups_ $mod_ident _instance>];
 
-                NssStatus::Success as i32
+        impl $crate::initgroups::InitgroupsHooks for [<libnss_initgroups_ $mod_ident _instance>] {
+            fn get_entries_by_user(user: String) -> $crate::interop::Response<Vec<$crate::group::Group>> {
+                <$backend as $crate::initgroups::InitgroupsBackend>::get_entries_by_user(*[<INITGROUPS_ $mod_ident _INSTANCE>], user)
             }
         }
+
+        $crate::libnss_initgroups_hooks!($mod_ident, [<libnss_initgroups_ $mod_ident _instance>]);
     }
-)
+);
 }
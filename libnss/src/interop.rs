@@ -1,9 +1,294 @@
-use std::collections::VecDeque;
-use std::ffi::CString;
+use std::any::{Any, TypeId};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt;
 use std::io;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::thread::LocalKey;
+
+type PanicHandler = Box<dyn Fn(&str) + Send + Sync>;
+type BufferSizeHandler = Box<dyn Fn(usize, usize) + Send + Sync>;
+
+lazy_static::lazy_static! {
+    static ref PANIC_HANDLER: RwLock<Option<PanicHandler>> = RwLock::new(None);
+    static ref BUFFER_SIZE_HANDLER: RwLock<Option<BufferSizeHandler>> = RwLock::new(None);
+    static ref INSTANCES: Mutex<HashMap<TypeId, &'static (dyn Any + Send + Sync)>> =
+        Mutex::new(HashMap::new());
+}
+
+/// Sets a handler which is called with the message of any panic caught at an NSS entry point.
+///
+/// Panics are still reported by the standard panic hook before they are caught. Panics can only be
+/// caught if the module is built with `panic = "unwind"`.
+pub fn set_panic_handler<F: Fn(&str) + Send + Sync + 'static>(handler: F) {
+    if let Ok(mut current) = PANIC_HANDLER.write() {
+        *current = Some(Box::new(handler));
+    }
+}
+
+fn report_panic(payload: &(dyn Any + Send)) {
+    let message = if let Some(message) = payload.downcast_ref::<&str>() {
+        message
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.as_str()
+    } else {
+        "Box<dyn Any>"
+    };
+
+    if let Ok(handler) = PANIC_HANDLER.read() {
+        if let Some(handler) = handler.as_ref() {
+            // A panicking handler must not unwind into C either
+            let _ = panic::catch_unwind(AssertUnwindSafe(|| handler(message)));
+        }
+    }
+}
+
+/// Runs `f`, returning the result of `on_panic` instead of unwinding if it panics.
+pub fn catch_panic_or<R, F, P>(f: F, on_panic: P) -> R
+where
+    F: FnOnce() -> R,
+    P: FnOnce() -> R,
+{
+    match panic::catch_unwind(AssertUnwindSafe(f)) {
+        Ok(result) => result,
+        Err(payload) => {
+            report_panic(payload.as_ref());
+            on_panic()
+        }
+    }
+}
+
+/// Runs the body of an NSS entry point, turning a panic into `NssStatus::Unavail` with `ENOENT`.
+///
+/// # Safety
+///
+/// `errnop` must be null or valid for writes.
+pub unsafe fn catch_panic<F>(errnop: *mut libc::c_int, f: F) -> libc::c_int
+where
+    F: FnOnce() -> libc::c_int,
+{
+    catch_panic_or(f, || {
+        if !errnop.is_null() {
+            *errnop = libc::ENOENT;
+        }
+        NssStatus::Unavail as libc::c_int
+    })
+}
+
+/// Like `catch_panic`, additionally setting `h_errno` to `NO_RECOVERY` for the resolver databases.
+///
+/// # Safety
+///
+/// `errnop` and `h_errnop` must each be null or valid for writes.
+pub unsafe fn catch_panic_herrno<F>(
+    errnop: *mut libc::c_int,
+    h_errnop: *mut libc::c_int,
+    f: F,
+) -> libc::c_int
+where
+    F: FnOnce() -> libc::c_int,
+{
+    catch_panic_or(f, || {
+        if !errnop.is_null() {
+            *errnop = libc::ENOENT;
+        }
+        if !h_errnop.is_null() {
+            // NO_RECOVERY
+            *h_errnop = 3;
+        }
+        NssStatus::Unavail as libc::c_int
+    })
+}
+
+/// Sets a handler which is called with the required and the available size whenever a record is
+/// rejected up front because it does not fit the caller's buffer.
+///
+/// glibc retries such lookups with a larger buffer, so this is only useful for diagnostics.
+pub fn set_buffer_size_handler<F: Fn(usize, usize) + Send + Sync + 'static>(handler: F) {
+    if let Ok(mut current) = BUFFER_SIZE_HANDLER.write() {
+        *current = Some(Box::new(handler));
+    }
+}
+
+fn report_buffer_size(required: usize, available: usize) {
+    if let Ok(handler) = BUFFER_SIZE_HANDLER.read() {
+        if let Some(handler) = handler.as_ref() {
+            handler(required, available);
+        }
+    }
+}
+
+/// Returns the process wide instance of `T`, calling `new` if there is none yet.
+///
+/// Used by the `libnss_*_hooks!(name, Type = new)` macro forms, so a single backend object holding
+/// connection pools, configuration or caches serves every database it is exported for. Instances
+/// are shared by type: every invocation for the same `Type` returns the first instance constructed,
+/// the constructors of other invocations are not used. Use distinct types for backends which need
+/// a different configuration.
+///
+/// `new` runs without any lock held, so it may perform lookups served by other backends of the
+/// same module, for example resolving the host name of a server. It must not perform lookups
+/// served by `T` itself. If two threads construct `T` at the same time, both call `new` and only
+/// one result is kept.
+pub fn shared_instance<T: Any + Send + Sync, F: FnOnce() -> T>(new: F) -> &'static T {
+    let existing = INSTANCES
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .get(&TypeId::of::<T>())
+        .copied();
+
+    let instance = match existing {
+        Some(instance) => instance,
+        None => {
+            let created = new();
+            let mut instances = INSTANCES.lock().unwrap_or_else(|e| e.into_inner());
+            *instances
+                .entry(TypeId::of::<T>())
+                .or_insert_with(|| Box::leak(Box::new(created)))
+        }
+    };
+
+    match instance.downcast_ref::<T>() {
+        Some(instance) => instance,
+        None => unreachable!(),
+    }
+}
 
 pub trait ToC<C> {
-    unsafe fn to_c(&self, result: *mut C, buffer: &mut CBuffer) -> std::io::Result<()>;
+    /// # Safety
+    ///
+    /// `result` must be valid for writes and `buffer` must describe writable memory which outlives
+    /// the written record.
+    unsafe fn to_c(&self, result: *mut C, buffer: &mut CBuffer) -> Result<(), ToCError>;
+
+    /// Upper bound of the buffer space used by `to_c`, if known. Records which do not fit are
+    /// rejected with `ERANGE` before anything is written.
+    fn required_size(&self) -> Option<usize> {
+        None
+    }
+}
+
+impl ToC<*mut libc::c_char> for String {
+    unsafe fn to_c(
+        &self,
+        result: *mut *mut libc::c_char,
+        buffer: &mut CBuffer,
+    ) -> Result<(), ToCError> {
+        *result = buffer.write_str(self)?;
+        Ok(())
+    }
+
+    fn required_size(&self) -> Option<usize> {
+        Some(CBuffer::str_size(self))
+    }
+}
+
+/// How `CBuffer::write_str` handles strings containing interior NUL bytes
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum NulPolicy {
+    /// Reject the record, reporting it as `NotFound`
+    RejectNotFound = 0,
+    /// Reject the record, reporting it as `Unavail`
+    RejectUnavail = 1,
+    /// Truncate the string at the first NUL byte
+    Truncate = 2,
+}
+
+static NUL_POLICY: AtomicU8 = AtomicU8::new(NulPolicy::RejectUnavail as u8);
+
+/// Sets the process wide policy for strings containing NUL bytes, defaults to `RejectUnavail`
+pub fn set_nul_policy(policy: NulPolicy) {
+    NUL_POLICY.store(policy as u8, Ordering::Relaxed);
+}
+
+pub fn nul_policy() -> NulPolicy {
+    match NUL_POLICY.load(Ordering::Relaxed) {
+        0 => NulPolicy::RejectNotFound,
+        2 => NulPolicy::Truncate,
+        _ => NulPolicy::RejectUnavail,
+    }
+}
+
+/// Error returned by `CBuffer::write_str` when a string is rejected due to an interior NUL byte
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NulByteError {
+    pub position: usize,
+}
+
+impl fmt::Display for NulByteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "string contains a NUL byte at position {}", self.position)
+    }
+}
+
+impl std::error::Error for NulByteError {}
+
+/// Error returned by `ToC` implementations, deciding the status and errno reported to glibc
+#[derive(Debug)]
+pub enum ToCError {
+    /// The caller's buffer is too small, glibc retries with a larger one
+    BufferTooSmall,
+    /// A value cannot be represented in C, handled according to the `NulPolicy`
+    Encoding(NulByteError),
+    /// Any other failure, reported as temporary for `WouldBlock`, `Interrupted` and `TimedOut`
+    Backend(io::Error),
+}
+
+impl ToCError {
+    /// Returns the status and errno pair glibc documents for the `*_r` functions
+    pub fn to_status(&self) -> (NssStatus, libc::c_int) {
+        match self {
+            ToCError::BufferTooSmall => (NssStatus::TryAgain, libc::ERANGE),
+            ToCError::Encoding(_) => match nul_policy() {
+                NulPolicy::RejectNotFound => (NssStatus::NotFound, libc::ENOENT),
+                _ => (NssStatus::Unavail, libc::ENOENT),
+            },
+            ToCError::Backend(e) => match e.kind() {
+                io::ErrorKind::WouldBlock | io::ErrorKind::Interrupted | io::ErrorKind::TimedOut => {
+                    (NssStatus::TryAgain, libc::EAGAIN)
+                }
+                _ => (NssStatus::Unavail, libc::ENOENT),
+            },
+        }
+    }
+}
+
+impl fmt::Display for ToCError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ToCError::BufferTooSmall => write!(f, "buffer too small"),
+            ToCError::Encoding(e) => write!(f, "encoding error: {}", e),
+            ToCError::Backend(e) => write!(f, "backend error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ToCError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ToCError::BufferTooSmall => None,
+            ToCError::Encoding(e) => Some(e),
+            ToCError::Backend(e) => Some(e),
+        }
+    }
+}
+
+impl From<NulByteError> for ToCError {
+    fn from(e: NulByteError) -> Self {
+        ToCError::Encoding(e)
+    }
+}
+
+impl From<io::Error> for ToCError {
+    fn from(e: io::Error) -> Self {
+        if e.raw_os_error() == Some(libc::ERANGE) {
+            return ToCError::BufferTooSmall;
+        }
+
+        ToCError::Backend(e)
+    }
 }
 
 #[allow(dead_code)]
@@ -37,6 +322,21 @@ impl<R> Response<R> {
         }
     }
 
+    /// Converts the value of a successful response, keeping any other response as it is
+    pub fn map<U, F: FnOnce(R) -> U>(self, f: F) -> Response<U> {
+        match self {
+            Self::Success(value) => Response::Success(f(value)),
+            Self::TryAgain => Response::TryAgain,
+            Self::Unavail => Response::Unavail,
+            Self::NotFound => Response::NotFound,
+            Self::Return => Response::Return,
+        }
+    }
+
+    /// # Safety
+    ///
+    /// `result` and `errnop` must be valid for writes and `buf` must point to `buflen` writable
+    /// bytes, as passed to the `*_r` functions by glibc.
     pub unsafe fn to_c<C>(
         &self,
         result: *mut C,
@@ -48,72 +348,332 @@ impl<R> Response<R> {
         R: ToC<C>,
     {
         if let Self::Success(entity) = self {
+            if let Some(required) = entity.required_size() {
+                if required > buflen {
+                    report_buffer_size(required, buflen);
+                    let (status, errno) = ToCError::BufferTooSmall.to_status();
+                    *errnop = errno;
+                    return status;
+                }
+            }
+
+            // The buffer is not cleared, every write terminates its own data so only the bytes
+            // actually used by the entity are initialized
             let mut buffer = CBuffer::new(buf as *mut libc::c_void, buflen);
-            buffer.clear();
 
             match entity.to_c(result, &mut buffer) {
                 Ok(()) => {
                     *errnop = 0;
                     self.to_status()
                 }
-                Err(e) => match e.raw_os_error() {
-                    Some(e) => {
-                        *errnop = e;
-                        Self::TryAgain.to_status()
-                    }
-                    None => {
-                        *errnop = libc::ENOENT;
-                        Self::Unavail.to_status()
-                    }
-                },
+                Err(e) => {
+                    let (status, errno) = e.to_status();
+                    *errnop = errno;
+                    status
+                }
             }
         } else {
+            // glibc reads errno for every status, a stale ERANGE would make it retry forever
+            *errnop = match self {
+                Self::TryAgain => libc::EAGAIN,
+                Self::Return => 0,
+                _ => libc::ENOENT,
+            };
             self.to_status()
         }
     }
 }
 
+/// Entries produced lazily for `get*ent_r`, returned by the `open_enumeration` hooks.
+///
+/// `set*ent` opens the enumeration and every `get*ent_r` pulls the next entry from it, so entries
+/// do not need to be materialized up front. The hooks default to the entries returned by
+/// `get_all_entries`. Items other than `Success` are returned to the caller as they are, the
+/// enumeration ends when the iterator returns `None`.
+pub type Enumeration<T> = Box<dyn std::iter::Iterator<Item = Response<T>> + Send>;
+
+/// Wraps already materialized entries, as returned by `get_all_entries`, in an `Enumeration`
+pub fn enumerate<T: Send + 'static>(entries: Vec<T>) -> Enumeration<T> {
+    Box::new(entries.into_iter().map(Response::Success))
+}
+
+/// Enumeration owned by a single caller, only used for the netgroup state glibc keeps in the
+/// `__netgrent` passed to every netgroup call.
+///
+/// As glibc owns that state, it needs neither the locking nor the per-thread cursors of
+/// `EnumerationState`, which serves all other databases. Both replay an entry which did not fit
+/// the caller's buffer in the same way.
 pub struct Iterator<T> {
-    items: Option<VecDeque<T>>,
-    index: usize,
+    entries: Option<Enumeration<T>>,
+    current: Option<Response<T>>,
+    replay: bool,
 }
 
-impl<T: Clone> Iterator<T> {
+impl<T: Clone + Send + 'static> Iterator<T> {
     pub fn new() -> Self {
-        Iterator { items: None, index: 0 }
+        Iterator {
+            entries: None,
+            current: None,
+            replay: false,
+        }
     }
+
     pub fn open(&mut self, items: Vec<T>) -> NssStatus {
-        self.items = Some(VecDeque::from(items));
-        self.index = 0;
+        self.open_enumeration(enumerate(items))
+    }
+
+    pub fn open_enumeration(&mut self, entries: Enumeration<T>) -> NssStatus {
+        self.entries = Some(entries);
+        self.current = None;
+        self.replay = false;
         NssStatus::Success
     }
 
+    #[allow(clippy::should_implement_trait)]
     pub fn next(&mut self) -> Response<T> {
-        let response = match self.items {
-            Some(ref mut items) => match items.get(self.index) {
-                Some(entity) => Response::Success(entity.clone()),
-                None => Response::NotFound,
-            },
-            None => Response::Unavail,
+        let entries = match self.entries {
+            Some(ref mut entries) => entries,
+            None => return Response::Unavail,
         };
-        self.index += 1;
 
-        return response;
+        if !self.replay {
+            self.current = entries.next();
+        }
+        self.replay = false;
+
+        match self.current {
+            Some(ref response) => response.clone(),
+            None => Response::NotFound,
+        }
     }
 
+    /// Returns the current entry again on the next call to `next`, as the caller's buffer was too
+    /// small for it. Only successful entries are replayed, any other response is not repeated.
     pub fn previous(&mut self) {
-        if self.index > 0 {
-            self.index -= 1;
+        if let Some(Response::Success(_)) = self.current {
+            self.replay = true;
         }
     }
 
     pub fn close(&mut self) -> NssStatus {
-        self.items = None;
-        self.index = 0;
+        self.entries = None;
+        self.current = None;
+        self.replay = false;
         NssStatus::Success
     }
 }
 
+impl<T: Clone + Send + 'static> Default for Iterator<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Whether the `set*ent`, `get*ent_r` and `end*ent` calls of a database share their position.
+///
+/// Selected per database by the `enumeration_mode` hooks, which default to `Process`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum EnumerationMode {
+    /// One enumeration shared by all threads of the process, as with glibc's own modules
+    Process,
+    /// Every thread enumerates independently
+    Thread,
+}
+
+/// Position within an enumeration, see `EnumerationState`
+pub struct EnumerationCursor<T> {
+    entries: Option<Arc<Mutex<Enumeration<T>>>>,
+    current: Option<Response<T>>,
+    replay: bool,
+    /// Set while the cursor is updated, still set afterwards if the update panicked
+    updating: bool,
+}
+
+impl<T> EnumerationCursor<T> {
+    pub fn new() -> Self {
+        EnumerationCursor {
+            entries: None,
+            current: None,
+            replay: false,
+            updating: false,
+        }
+    }
+
+    /// Runs `f` unless an earlier update panicked, in which case the cursor is reset and `None` is
+    /// returned
+    fn update<R, F: FnOnce(&mut Self) -> R>(&mut self, f: F) -> Option<R> {
+        if self.updating {
+            *self = EnumerationCursor::new();
+            return None;
+        }
+
+        self.updating = true;
+        let result = f(self);
+        self.updating = false;
+        Some(result)
+    }
+}
+
+impl<T> Default for EnumerationCursor<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Enumeration state of a database, used by the generated `set*ent`, `get*ent_r` and `end*ent`.
+///
+/// Hooks are never called while the state is locked, so a slow backend does not block other
+/// threads from starting or ending their enumeration. In `EnumerationMode::Thread` a thread which
+/// calls `get*ent_r` without an enumeration of its own implicitly starts one, as glibc only calls
+/// `set*ent` once per process.
+pub struct EnumerationState<T: 'static> {
+    mode: EnumerationMode,
+    open: fn() -> Response<Enumeration<T>>,
+    process: Mutex<EnumerationCursor<T>>,
+    thread: &'static LocalKey<RefCell<EnumerationCursor<T>>>,
+}
+
+impl<T: Clone + Send + 'static> EnumerationState<T> {
+    pub fn new(
+        mode: EnumerationMode,
+        open: fn() -> Response<Enumeration<T>>,
+        thread: &'static LocalKey<RefCell<EnumerationCursor<T>>>,
+    ) -> Self {
+        EnumerationState {
+            mode,
+            open,
+            process: Mutex::new(EnumerationCursor::new()),
+            thread,
+        }
+    }
+
+    /// Runs `f` on the cursor of the current thread or process.
+    ///
+    /// If a panic interrupted an earlier update, the enumeration is reset and `None` is returned so
+    /// the caller reports `Unavail` once, later calls work normally. The poison flag of the lock is
+    /// ignored, the cursor tracks interrupted updates itself.
+    fn with_cursor<R, F: FnOnce(&mut EnumerationCursor<T>) -> R>(&self, f: F) -> Option<R> {
+        match self.mode {
+            EnumerationMode::Process => self
+                .process
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .update(f),
+            EnumerationMode::Thread => self
+                .thread
+                .try_with(|cursor| {
+                    cursor
+                        .try_borrow_mut()
+                        .ok()
+                        .and_then(|mut cursor| cursor.update(f))
+                })
+                .ok()
+                .flatten(),
+        }
+    }
+
+    /// Starts a new enumeration
+    pub fn set(&self) -> NssStatus {
+        match (self.open)() {
+            Response::Success(entries) => {
+                let entries = Arc::new(Mutex::new(entries));
+                let opened = self.with_cursor(|cursor| {
+                    cursor.entries = Some(entries);
+                    cursor.current = None;
+                    cursor.replay = false;
+                });
+
+                match opened {
+                    Some(()) => NssStatus::Success,
+                    None => NssStatus::Unavail,
+                }
+            }
+            response => response.to_status(),
+        }
+    }
+
+    /// Passes the next entry to `write`, returning the same entry again on the next call if it was
+    /// successful but `write` reports `TryAgain` with `ERANGE`, as glibc then retries with a larger
+    /// buffer. Any other response is not repeated, the next call pulls a fresh entry.
+    ///
+    /// # Safety
+    ///
+    /// `errnop` must be valid for reads once `write` returns, it is the errno set by `write`.
+    pub unsafe fn get<F: FnOnce(Response<T>) -> NssStatus>(
+        &self,
+        errnop: *const libc::c_int,
+        write: F,
+    ) -> NssStatus {
+        let next = self.with_cursor(|cursor| {
+            if cursor.replay {
+                cursor.replay = false;
+                Ok(cursor.current.clone().unwrap_or(Response::NotFound))
+            } else {
+                Err(cursor.entries.clone())
+            }
+        });
+
+        let next = match next {
+            Some(Err(None)) if self.mode == EnumerationMode::Thread => match self.set() {
+                NssStatus::Success => self
+                    .with_cursor(|cursor| cursor.entries.clone())
+                    .map(Err),
+                status => return status,
+            },
+            next => next,
+        };
+
+        let response = match next {
+            None => Response::Unavail,
+            Some(Ok(response)) => response,
+            Some(Err(None)) => Response::Unavail,
+            Some(Err(Some(entries))) => {
+                // Only the enumeration itself is locked while pulling from the hook
+                let entry = match entries.lock() {
+                    Ok(mut entries) => entries.next(),
+                    Err(_) => {
+                        // A hook panicked while producing an entry, the enumeration can't continue
+                        self.with_cursor(|cursor| {
+                            if is_same(&cursor.entries, &entries) {
+                                *cursor = EnumerationCursor::new();
+                            }
+                        });
+                        return write(Response::Unavail);
+                    }
+                };
+
+                self.with_cursor(|cursor| {
+                    if is_same(&cursor.entries, &entries) {
+                        cursor.current = entry.clone();
+                    }
+                });
+
+                entry.unwrap_or(Response::NotFound)
+            }
+        };
+
+        let success = matches!(response, Response::Success(_));
+        let status = write(response);
+        if success && status == NssStatus::TryAgain && *errnop == libc::ERANGE {
+            self.with_cursor(|cursor| cursor.replay = cursor.current.is_some());
+        }
+        status
+    }
+
+    pub fn end(&self) -> NssStatus {
+        let ended = self.with_cursor(|cursor| *cursor = EnumerationCursor::new());
+
+        match ended {
+            Some(()) => NssStatus::Success,
+            None => NssStatus::Unavail,
+        }
+    }
+}
+
+fn is_same<E>(current: &Option<Arc<E>>, entries: &Arc<E>) -> bool {
+    matches!(current, Some(current) if Arc::ptr_eq(current, entries))
+}
+
 pub struct CBuffer {
     start: *mut libc::c_void,
     pos: *mut libc::c_void,
@@ -131,41 +691,72 @@ impl CBuffer {
         }
     }
 
+    /// Space used by `write_str`
+    pub fn str_size(string: &str) -> usize {
+        string.len() + 1
+    }
+
+    /// Upper bound of the space used by `write_strs`
+    pub fn strs_size<S: AsRef<str>>(strings: &[S]) -> usize {
+        Self::aligned_size::<*mut libc::c_char>(strings.len() + 1)
+            + strings
+                .iter()
+                .map(|s| Self::str_size(s.as_ref()))
+                .sum::<usize>()
+    }
+
+    /// Upper bound of the space used by `reserve_aligned`, assuming the worst case padding
+    pub fn aligned_size<T>(count: usize) -> usize {
+        std::mem::size_of::<T>() * count + std::mem::align_of::<T>() - 1
+    }
+
+    /// # Safety
+    ///
+    /// The buffer must describe writable memory of the length it was created with.
     pub unsafe fn clear(&mut self) {
         libc::memset(self.start, 0, self.len);
     }
 
-    pub unsafe fn write_str(&mut self, string: &str) -> io::Result<*mut libc::c_char> {
+    /// # Safety
+    ///
+    /// The buffer must describe writable memory of the length it was created with.
+    pub unsafe fn write_str(&mut self, string: &str) -> Result<*mut libc::c_char, ToCError> {
         // Capture start address
         let str_start = self.pos;
 
-        // Convert string
-        let cstr = CString::new(string).expect("Failed to convert string");
-        let ptr = cstr.as_ptr();
-        let len = libc::strlen(ptr);
+        // Handle interior NUL bytes according to the configured policy
+        let mut bytes = string.as_bytes();
+        if let Some(position) = bytes.iter().position(|b| *b == 0) {
+            match nul_policy() {
+                NulPolicy::Truncate => bytes = &bytes[..position],
+                _ => return Err(ToCError::Encoding(NulByteError { position })),
+            }
+        }
+        let len = bytes.len();
 
         // Ensure we have enough capacity
         if self.free < len + 1 {
-            return Err(io::Error::from_raw_os_error(libc::ERANGE));
+            return Err(ToCError::BufferTooSmall);
         }
 
-        // Copy string
-        libc::memcpy(self.pos, ptr as *mut libc::c_void, len);
+        // Copy string and terminator
+        libc::memcpy(self.pos, bytes.as_ptr() as *const libc::c_void, len);
+        *(self.pos as *mut u8).add(len) = 0;
         self.pos = self.pos.offset(len as isize + 1);
-        self.free -= len as usize + 1;
+        self.free -= len + 1;
 
         // Return start of string
         Ok(str_start as *mut libc::c_char)
     }
 
+    /// # Safety
+    ///
+    /// The buffer must describe writable memory of the length it was created with.
     pub unsafe fn write_strs<S: AsRef<str>>(
         &mut self,
         strings: &[S],
-    ) -> io::Result<*mut *mut libc::c_char> {
-        let ptr_size = std::mem::size_of::<*mut libc::c_char>() as isize;
-
-        let vec_start =
-            self.reserve(ptr_size * (strings.len() as isize + 1))? as *mut *mut libc::c_char;
+    ) -> Result<*mut *mut libc::c_char, ToCError> {
+        let vec_start = self.reserve_aligned::<*mut libc::c_char>(strings.len() + 1)?;
         let mut pos = vec_start;
 
         // Write strings
@@ -174,23 +765,127 @@ impl CBuffer {
             pos = pos.offset(1);
         }
 
-        libc::memset(pos as *mut libc::c_void, 0, ptr_size as usize);
+        pos.write(std::ptr::null_mut());
 
         Ok(vec_start)
     }
 
-    pub unsafe fn reserve(&mut self, len: isize) -> io::Result<*mut libc::c_char> {
+    /// Reserves space for `count` values of `T`, correctly aligned for `T`
+    ///
+    /// # Safety
+    ///
+    /// The buffer must describe writable memory of the length it was created with.
+    pub unsafe fn reserve_aligned<T>(&mut self, count: usize) -> Result<*mut T, ToCError> {
+        let padding = (self.pos as *mut u8).align_offset(std::mem::align_of::<T>());
+        let len = std::mem::size_of::<T>()
+            .checked_mul(count)
+            .and_then(|len| len.checked_add(padding))
+            .ok_or(ToCError::BufferTooSmall)?;
+
+        let start = self.reserve(len as isize)?;
+        Ok(start.add(padding) as *mut T)
+    }
+
+    /// # Safety
+    ///
+    /// The buffer must describe writable memory of the length it was created with.
+    pub unsafe fn reserve(&mut self, len: isize) -> Result<*mut libc::c_char, ToCError> {
         let start = self.pos;
 
         // Ensure we have enough capacity
         if self.free < len as usize {
-            return Err(io::Error::from_raw_os_error(libc::ERANGE));
+            return Err(ToCError::BufferTooSmall);
         }
 
         // Reserve space
-        self.pos = self.pos.offset(len as isize);
+        self.pos = self.pos.offset(len);
         self.free -= len as usize;
 
         Ok(start as *mut libc::c_char)
     }
 }
+
+/// Typed handle to data written into a `CBuffer` by a `CWriter`
+#[derive(Debug)]
+pub struct CPtr<T> {
+    ptr: *mut T,
+}
+
+impl<T> CPtr<T> {
+    pub fn as_ptr(&self) -> *mut T {
+        self.ptr
+    }
+}
+
+impl<T> Clone for CPtr<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for CPtr<T> {}
+
+/// Safe writer over a `CBuffer`, for use by `ToC` implementations.
+///
+/// Every method checks the remaining capacity, aligns the data for its type and returns
+/// `ToCError::BufferTooSmall` if the buffer is too small.
+pub struct CWriter<'a> {
+    buffer: &'a mut CBuffer,
+}
+
+impl<'a> CWriter<'a> {
+    /// # Safety
+    ///
+    /// `buffer` must describe writable memory which outlives all returned handles, as is the case
+    /// for the buffer passed to `ToC::to_c`.
+    pub unsafe fn new(buffer: &'a mut CBuffer) -> Self {
+        CWriter { buffer }
+    }
+
+    /// Writes a NUL terminated string
+    pub fn str(&mut self, string: &str) -> Result<CPtr<libc::c_char>, ToCError> {
+        let ptr = unsafe { self.buffer.write_str(string)? };
+        Ok(CPtr { ptr })
+    }
+
+    /// Writes a NULL terminated array of NUL terminated strings
+    pub fn strs<S: AsRef<str>>(&mut self, strings: &[S]) -> Result<CPtr<*mut libc::c_char>, ToCError> {
+        let ptr = unsafe { self.buffer.write_strs(strings)? };
+        Ok(CPtr { ptr })
+    }
+
+    /// Writes a copy of `bytes`
+    pub fn bytes(&mut self, bytes: &[u8]) -> Result<CPtr<libc::c_char>, ToCError> {
+        unsafe {
+            let ptr = self.buffer.reserve_aligned::<libc::c_char>(bytes.len())?;
+            std::ptr::copy_nonoverlapping(bytes.as_ptr(), ptr as *mut u8, bytes.len());
+            Ok(CPtr { ptr })
+        }
+    }
+
+    /// Writes a NULL terminated array of pointers to copies of `blobs`, such as `h_addr_list`
+    pub fn blobs<B: AsRef<[u8]>>(&mut self, blobs: &[B]) -> Result<CPtr<*mut libc::c_char>, ToCError> {
+        unsafe {
+            let array = self
+                .buffer
+                .reserve_aligned::<*mut libc::c_char>(blobs.len() + 1)?;
+
+            for (i, blob) in blobs.iter().enumerate() {
+                let blob = self.bytes(blob.as_ref())?;
+                array.add(i).write(blob.as_ptr());
+            }
+            array.add(blobs.len()).write(std::ptr::null_mut());
+
+            Ok(CPtr { ptr: array })
+        }
+    }
+
+    /// Writes a plain value, aligned for its type
+    pub fn value<T: Copy>(&mut self, value: T) -> Result<CPtr<T>, ToCError> {
+        unsafe {
+            let ptr = self.buffer.reserve_aligned::<T>(1)?;
+            ptr.write(value);
+            Ok(CPtr { ptr })
+        }
+    }
+}
@@ -1,9 +1,19 @@
+pub mod alias;
+pub mod ethers;
 pub mod group;
+pub mod gshadow;
 pub mod host;
 pub mod initgroups;
 pub mod interop;
+pub mod module;
+pub mod netgroup;
+pub mod network;
 pub mod passwd;
+pub mod protocol;
+pub mod rpc;
+pub mod service;
 pub mod shadow;
+pub mod subid;
 
 /// Re-exports for use by macros
 #[doc(hidden)]
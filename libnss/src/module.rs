@@ -0,0 +1,602 @@
+//! A single backend for every database
+//!
+//! `NssModule` combines the hooks of all databases into one instance based trait, where every
+//! method defaults to `Unavail`. `libnss_module!(name, Type)` exports the symbols of all databases
+//! for it, so a backend only implements the lookups it supports and glibc moves on to the next
+//! service for the others. The subid plugin is not included, as its symbols are not prefixed with
+//! the module name; use `libnss_subid_hooks!` for it.
+use crate::alias::Alias;
+use crate::ethers::Ether;
+use crate::group::Group;
+use crate::gshadow::GShadow;
+use crate::host::{canonical_name, hosts_by_name, AddressFamily, Host};
+use crate::interop::{enumerate, Enumeration, EnumerationMode, Response};
+use crate::netgroup::NetgroupEntry;
+use crate::network::Network;
+use crate::passwd::Passwd;
+use crate::protocol::Protocol;
+use crate::rpc::Rpc;
+use crate::service::Service;
+use crate::shadow::Shadow;
+use std::net::IpAddr;
+
+pub trait NssModule {
+    /// See `EnumerationMode`, applies to every database of the module unless its own
+    /// `*_enumeration_mode` method is overridden
+    fn enumeration_mode(&self) -> EnumerationMode {
+        EnumerationMode::Process
+    }
+
+    // passwd
+    fn get_all_passwd(&self) -> Response<Vec<Passwd>> {
+        Response::Unavail
+    }
+
+    fn open_passwd_enumeration(&self) -> Response<Enumeration<Passwd>> {
+        self.get_all_passwd().map(enumerate)
+    }
+
+    fn passwd_enumeration_mode(&self) -> EnumerationMode {
+        self.enumeration_mode()
+    }
+
+    fn get_passwd_by_uid(&self, _uid: libc::uid_t) -> Response<Passwd> {
+        Response::Unavail
+    }
+
+    fn get_passwd_by_name(&self, _name: String) -> Response<Passwd> {
+        Response::Unavail
+    }
+
+    // group
+    fn get_all_groups(&self) -> Response<Vec<Group>> {
+        Response::Unavail
+    }
+
+    fn open_group_enumeration(&self) -> Response<Enumeration<Group>> {
+        self.get_all_groups().map(enumerate)
+    }
+
+    fn group_enumeration_mode(&self) -> EnumerationMode {
+        self.enumeration_mode()
+    }
+
+    fn get_group_by_gid(&self, _gid: libc::gid_t) -> Response<Group> {
+        Response::Unavail
+    }
+
+    fn get_group_by_name(&self, _name: String) -> Response<Group> {
+        Response::Unavail
+    }
+
+    // shadow
+    fn get_all_shadow(&self) -> Response<Vec<Shadow>> {
+        Response::Unavail
+    }
+
+    fn open_shadow_enumeration(&self) -> Response<Enumeration<Shadow>> {
+        self.get_all_shadow().map(enumerate)
+    }
+
+    fn shadow_enumeration_mode(&self) -> EnumerationMode {
+        self.enumeration_mode()
+    }
+
+    fn get_shadow_by_name(&self, _name: String) -> Response<Shadow> {
+        Response::Unavail
+    }
+
+    // gshadow
+    fn get_all_gshadow(&self) -> Response<Vec<GShadow>> {
+        Response::Unavail
+    }
+
+    fn open_gshadow_enumeration(&self) -> Response<Enumeration<GShadow>> {
+        self.get_all_gshadow().map(enumerate)
+    }
+
+    fn gshadow_enumeration_mode(&self) -> EnumerationMode {
+        self.enumeration_mode()
+    }
+
+    fn get_gshadow_by_name(&self, _name: String) -> Response<GShadow> {
+        Response::Unavail
+    }
+
+    // initgroups
+    /// Defaults to the groups listing `user` as a member. glibc only enumerates the groups itself
+    /// if `initgroups_dyn` is missing, which `libnss_module!` always exports.
+    fn get_initgroups_by_user(&self, user: String) -> Response<Vec<Group>> {
+        self.get_all_groups().map(|groups| {
+            groups
+                .into_iter()
+                .filter(|group| group.members.contains(&user))
+                .collect()
+        })
+    }
+
+    // hosts
+    fn get_all_hosts(&self) -> Response<Vec<Host>> {
+        Response::Unavail
+    }
+
+    fn open_host_enumeration(&self) -> Response<Enumeration<Host>> {
+        self.get_all_hosts().map(enumerate)
+    }
+
+    fn host_enumeration_mode(&self) -> EnumerationMode {
+        self.enumeration_mode()
+    }
+
+    fn get_host_by_name(&self, _name: &str, _family: AddressFamily) -> Response<Host> {
+        Response::Unavail
+    }
+
+    fn get_host_by_addr(&self, _addr: IpAddr) -> Response<Host> {
+        Response::Unavail
+    }
+
+    fn get_hosts_by_name(&self, name: &str) -> Response<Vec<Host>> {
+        hosts_by_name(|family| self.get_host_by_name(name, family))
+    }
+
+    fn get_canonical_name(&self, name: &str) -> Response<String> {
+        canonical_name(|family| self.get_host_by_name(name, family))
+    }
+
+    // services
+    fn get_all_services(&self) -> Response<Vec<Service>> {
+        Response::Unavail
+    }
+
+    fn open_service_enumeration(&self) -> Response<Enumeration<Service>> {
+        self.get_all_services().map(enumerate)
+    }
+
+    fn service_enumeration_mode(&self) -> EnumerationMode {
+        self.enumeration_mode()
+    }
+
+    fn get_service_by_name(&self, _name: String, _proto: Option<String>) -> Response<Service> {
+        Response::Unavail
+    }
+
+    fn get_service_by_port(&self, _port: u16, _proto: Option<String>) -> Response<Service> {
+        Response::Unavail
+    }
+
+    // protocols
+    fn get_all_protocols(&self) -> Response<Vec<Protocol>> {
+        Response::Unavail
+    }
+
+    fn open_protocol_enumeration(&self) -> Response<Enumeration<Protocol>> {
+        self.get_all_protocols().map(enumerate)
+    }
+
+    fn protocol_enumeration_mode(&self) -> EnumerationMode {
+        self.enumeration_mode()
+    }
+
+    fn get_protocol_by_name(&self, _name: String) -> Response<Protocol> {
+        Response::Unavail
+    }
+
+    fn get_protocol_by_number(&self, _number: i32) -> Response<Protocol> {
+        Response::Unavail
+    }
+
+    // networks
+    fn get_all_networks(&self) -> Response<Vec<Network>> {
+        Response::Unavail
+    }
+
+    fn open_network_enumeration(&self) -> Response<Enumeration<Network>> {
+        self.get_all_networks().map(enumerate)
+    }
+
+    fn network_enumeration_mode(&self) -> EnumerationMode {
+        self.enumeration_mode()
+    }
+
+    fn get_network_by_name(&self, _name: String) -> Response<Network> {
+        Response::Unavail
+    }
+
+    fn get_network_by_addr(&self, _net: u32) -> Response<Network> {
+        Response::Unavail
+    }
+
+    // ethers
+    fn get_all_ethers(&self) -> Response<Vec<Ether>> {
+        Response::Unavail
+    }
+
+    fn open_ethers_enumeration(&self) -> Response<Enumeration<Ether>> {
+        self.get_all_ethers().map(enumerate)
+    }
+
+    fn ethers_enumeration_mode(&self) -> EnumerationMode {
+        self.enumeration_mode()
+    }
+
+    fn get_ether_by_name(&self, _name: String) -> Response<Ether> {
+        Response::Unavail
+    }
+
+    fn get_ether_by_addr(&self, _addr: [u8; 6]) -> Response<Ether> {
+        Response::Unavail
+    }
+
+    // rpc
+    fn get_all_rpcs(&self) -> Response<Vec<Rpc>> {
+        Response::Unavail
+    }
+
+    fn open_rpc_enumeration(&self) -> Response<Enumeration<Rpc>> {
+        self.get_all_rpcs().map(enumerate)
+    }
+
+    fn rpc_enumeration_mode(&self) -> EnumerationMode {
+        self.enumeration_mode()
+    }
+
+    fn get_rpc_by_name(&self, _name: String) -> Response<Rpc> {
+        Response::Unavail
+    }
+
+    fn get_rpc_by_number(&self, _number: i32) -> Response<Rpc> {
+        Response::Unavail
+    }
+
+    // netgroup
+    fn get_netgroup_entries(&self, _netgroup: String) -> Response<Vec<NetgroupEntry>> {
+        Response::Unavail
+    }
+
+    // aliases
+    fn get_all_aliases(&self) -> Response<Vec<Alias>> {
+        Response::Unavail
+    }
+
+    fn open_alias_enumeration(&self) -> Response<Enumeration<Alias>> {
+        self.get_all_aliases().map(enumerate)
+    }
+
+    fn alias_enumeration_mode(&self) -> EnumerationMode {
+        self.enumeration_mode()
+    }
+
+    fn get_alias_by_name(&self, _name: String) -> Response<Alias> {
+        Response::Unavail
+    }
+}
+
+#[macro_export]
+macro_rules! libnss_module {
+($mod_ident:ident, $module:ty = $new:expr) => (
+    $crate::_macro_internal::paste! {
+        $crate::_macro_internal::lazy_static! {
+        static ref [<MODULE_ $mod_ident _INSTANCE>]: &'static $module = $crate::interop::shared_instance(|| $new);
+        }
+
+        #[allow(non_camel_case_types)]
+        struct [<libnss_module_ $mod_ident _passwd>];
+
+        impl $crate::passwd::PasswdHooks for [<libnss_module_ $mod_ident _passwd>] {
+            fn get_all_entries() -> $crate::interop::Response<Vec<$crate::passwd::Passwd>> {
+                <$module as $crate::module::NssModule>::get_all_passwd(*[<MODULE_ $mod_ident _INSTANCE>])
+            }
+
+            fn open_enumeration() -> $crate::interop::Response<$crate::interop::Enumeration<$crate::passwd::Passwd>> {
+                <$module as $crate::module::NssModule>::open_passwd_enumeration(*[<MODULE_ $mod_ident _INSTANCE>])
+            }
+
+            fn enumeration_mode() -> $crate::interop::EnumerationMode {
+                <$module as $crate::module::NssModule>::passwd_enumeration_mode(*[<MODULE_ $mod_ident _INSTANCE>])
+            }
+
+            fn get_entry_by_uid(uid: libc::uid_t) -> $crate::interop::Response<$crate::passwd::Passwd> {
+                <$module as $crate::module::NssModule>::get_passwd_by_uid(*[<MODULE_ $mod_ident _INSTANCE>], uid)
+            }
+
+            fn get_entry_by_name(name: String) -> $crate::interop::Response<$crate::passwd::Passwd> {
+                <$module as $crate::module::NssModule>::get_passwd_by_name(*[<MODULE_ $mod_ident _INSTANCE>], name)
+            }
+        }
+
+        $crate::libnss_passwd_hooks!($mod_ident, [<libnss_module_ $mod_ident _passwd>]);
+
+        #[allow(non_camel_case_types)]
+        struct [<libnss_module_ $mod_ident _group>];
+
+        impl $crate::group::GroupHooks for [<libnss_module_ $mod_ident _group>] {
+            fn get_all_entries() -> $crate::interop::Response<Vec<$crate::group::Group>> {
+                <$module as $crate::module::NssModule>::get_all_groups(*[<MODULE_ $mod_ident _INSTANCE>])
+            }
+
+            fn open_enumeration() -> $crate::interop::Response<$crate::interop::Enumeration<$crate::group::Group>> {
+                <$module as $crate::module::NssModule>::open_group_enumeration(*[<MODULE_ $mod_ident _INSTANCE>])
+            }
+
+            fn enumeration_mode() -> $crate::interop::EnumerationMode {
+                <$module as $crate::module::NssModule>::group_enumeration_mode(*[<MODULE_ $mod_ident _INSTANCE>])
+            }
+
+            fn get_entry_by_gid(gid: libc::gid_t) -> $crate::interop::Response<$crate::group::Group> {
+                <$module as $crate::module::NssModule>::get_group_by_gid(*[<MODULE_ $mod_ident _INSTANCE>], gid)
+            }
+
+            fn get_entry_by_name(name: String) -> $crate::interop::Response<$crate::group::Group> {
+                <$module as $crate::module::NssModule>::get_group_by_name(*[<MODULE_ $mod_ident _INSTANCE>], name)
+            }
+        }
+
+        $crate::libnss_group_hooks!($mod_ident, [<libnss_module_ $mod_ident _group>]);
+
+        #[allow(non_camel_case_types)]
+        struct [<libnss_module_ $mod_ident _shadow>];
+
+        impl $crate::shadow::ShadowHooks for [<libnss_module_ $mod_ident _shadow>] {
+            fn get_all_entries() -> $crate::interop::Response<Vec<$crate::shadow::Shadow>> {
+                <$module as $crate::module::NssModule>::get_all_shadow(*[<MODULE_ $mod_ident _INSTANCE>])
+            }
+
+            fn open_enumeration() -> $crate::interop::Response<$crate::interop::Enumeration<$crate::shadow::Shadow>> {
+                <$module as $crate::module::NssModule>::open_shadow_enumeration(*[<MODULE_ $mod_ident _INSTANCE>])
+            }
+
+            fn enumeration_mode() -> $crate::interop::EnumerationMode {
+                <$module as $crate::module::NssModule>::shadow_enumeration_mode(*[<MODULE_ $mod_ident _INSTANCE>])
+            }
+
+            fn get_entry_by_name(name: String) -> $crate::interop::Response<$crate::shadow::Shadow> {
+                <$module as $crate::module::NssModule>::get_shadow_by_name(*[<MODULE_ $mod_ident _INSTANCE>], name)
+            }
+        }
+
+        $crate::libnss_shadow_hooks!($mod_ident, [<libnss_module_ $mod_ident _shadow>]);
+
+        #[allow(non_camel_case_types)]
+        struct [<libnss_module_ $mod_ident _gshadow>];
+
+        impl $crate::gshadow::GShadowHooks for [<libnss_module_ $mod_ident _gshadow>] {
+            fn get_all_entries() -> $crate::interop::Response<Vec<$crate::gshadow::GShadow>> {
+                <$module as $crate::module::NssModule>::get_all_gshadow(*[<MODULE_ $mod_ident _INSTANCE>])
+            }
+
+            fn open_enumeration() -> $crate::interop::Response<$crate::interop::Enumeration<$crate::gshadow::GShadow>> {
+                <$module as $crate::module::NssModule>::open_gshadow_enumeration(*[<MODULE_ $mod_ident _INSTANCE>])
+            }
+
+            fn enumeration_mode() -> $crate::interop::EnumerationMode {
+                <$module as $crate::module::NssModule>::gshadow_enumeration_mode(*[<MODULE_ $mod_ident _INSTANCE>])
+            }
+
+            fn get_entry_by_name(name: String) -> $crate::interop::Response<$crate::gshadow::GShadow> {
+                <$module as $crate::module::NssModule>::get_gshadow_by_name(*[<MODULE_ $mod_ident _INSTANCE>], name)
+            }
+        }
+
+        $crate::libnss_gshadow_hooks!($mod_ident, [<libnss_module_ $mod_ident _gshadow>]);
+
+        #[allow(non_camel_case_types)]
+        struct [<libnss_module_ $mod_ident _initgroups>];
+
+        impl $crate::initgroups::InitgroupsHooks for [<libnss_module_ $mod_ident _initgroups>] {
+            fn get_entries_by_user(user: String) -> $crate::interop::Response<Vec<$crate::group::Group>> {
+                <$module as $crate::module::NssModule>::get_initgroups_by_user(*[<MODULE_ $mod_ident _INSTANCE>], user)
+            }
+        }
+
+        $crate::libnss_initgroups_hooks!($mod_ident, [<libnss_module_ $mod_ident _initgroups>]);
+
+        #[allow(non_camel_case_types)]
+        struct [<libnss_module_ $mod_ident _host>];
+
+        impl $crate::host::HostHooks for [<libnss_module_ $mod_ident _host>] {
+            fn get_all_entries() -> $crate::interop::Response<Vec<$crate::host::Host>> {
+                <$module as $crate::module::NssModule>::get_all_hosts(*[<MODULE_ $mod_ident _INSTANCE>])
+            }
+
+            fn open_enumeration() -> $crate::interop::Response<$crate::interop::Enumeration<$crate::host::Host>> {
+                <$module as $crate::module::NssModule>::open_host_enumeration(*[<MODULE_ $mod_ident _INSTANCE>])
+            }
+
+            fn enumeration_mode() -> $crate::interop::EnumerationMode {
+                <$module as $crate::module::NssModule>::host_enumeration_mode(*[<MODULE_ $mod_ident _INSTANCE>])
+            }
+
+            fn get_host_by_name(name: &str, family: $crate::host::AddressFamily) -> $crate::interop::Response<$crate::host::Host> {
+                <$module as $crate::module::NssModule>::get_host_by_name(*[<MODULE_ $mod_ident _INSTANCE>], name, family)
+            }
+
+            fn get_host_by_addr(addr: std::net::IpAddr) -> $crate::interop::Response<$crate::host::Host> {
+                <$module as $crate::module::NssModule>::get_host_by_addr(*[<MODULE_ $mod_ident _INSTANCE>], addr)
+            }
+
+            fn get_hosts_by_name(name: &str) -> $crate::interop::Response<Vec<$crate::host::Host>> {
+                <$module as $crate::module::NssModule>::get_hosts_by_name(*[<MODULE_ $mod_ident _INSTANCE>], name)
+            }
+
+            fn get_canonical_name(name: &str) -> $crate::interop::Response<String> {
+                <$module as $crate::module::NssModule>::get_canonical_name(*[<MODULE_ $mod_ident _INSTANCE>], name)
+            }
+        }
+
+        $crate::libnss_host_hooks!($mod_ident, [<libnss_module_ $mod_ident _host>]);
+
+        #[allow(non_camel_case_types)]
+        struct [<libnss_module_ $mod_ident _service>];
+
+        impl $crate::service::ServiceHooks for [<libnss_module_ $mod_ident _service>] {
+            fn get_all_entries() -> $crate::interop::Response<Vec<$crate::service::Service>> {
+                <$module as $crate::module::NssModule>::get_all_services(*[<MODULE_ $mod_ident _INSTANCE>])
+            }
+
+            fn open_enumeration() -> $crate::interop::Response<$crate::interop::Enumeration<$crate::service::Service>> {
+                <$module as $crate::module::NssModule>::open_service_enumeration(*[<MODULE_ $mod_ident _INSTANCE>])
+            }
+
+            fn enumeration_mode() -> $crate::interop::EnumerationMode {
+                <$module as $crate::module::NssModule>::service_enumeration_mode(*[<MODULE_ $mod_ident _INSTANCE>])
+            }
+
+            fn get_entry_by_name(name: String, proto: Option<String>) -> $crate::interop::Response<$crate::service::Service> {
+                <$module as $crate::module::NssModule>::get_service_by_name(*[<MODULE_ $mod_ident _INSTANCE>], name, proto)
+            }
+
+            fn get_entry_by_port(port: u16, proto: Option<String>) -> $crate::interop::Response<$crate::service::Service> {
+                <$module as $crate::module::NssModule>::get_service_by_port(*[<MODULE_ $mod_ident _INSTANCE>], port, proto)
+            }
+        }
+
+        $crate::libnss_service_hooks!($mod_ident, [<libnss_module_ $mod_ident _service>]);
+
+        #[allow(non_camel_case_types)]
+        struct [<libnss_module_ $mod_ident _protocol>];
+
+        impl $crate::protocol::ProtocolHooks for [<libnss_module_ $mod_ident _protocol>] {
+            fn get_all_entries() -> $crate::interop::Response<Vec<$crate::protocol::Protocol>> {
+                <$module as $crate::module::NssModule>::get_all_protocols(*[<MODULE_ $mod_ident _INSTANCE>])
+            }
+
+            fn open_enumeration() -> $crate::interop::Response<$crate::interop::Enumeration<$crate::protocol::Protocol>> {
+                <$module as $crate::module::NssModule>::open_protocol_enumeration(*[<MODULE_ $mod_ident _INSTANCE>])
+            }
+
+            fn enumeration_mode() -> $crate::interop::EnumerationMode {
+                <$module as $crate::module::NssModule>::protocol_enumeration_mode(*[<MODULE_ $mod_ident _INSTANCE>])
+            }
+
+            fn get_entry_by_name(name: String) -> $crate::interop::Response<$crate::protocol::Protocol> {
+                <$module as $crate::module::NssModule>::get_protocol_by_name(*[<MODULE_ $mod_ident _INSTANCE>], name)
+            }
+
+            fn get_entry_by_number(number: i32) -> $crate::interop::Response<$crate::protocol::Protocol> {
+                <$module as $crate::module::NssModule>::get_protocol_by_number(*[<MODULE_ $mod_ident _INSTANCE>], number)
+            }
+        }
+
+        $crate::libnss_protocol_hooks!($mod_ident, [<libnss_module_ $mod_ident _protocol>]);
+
+        #[allow(non_camel_case_types)]
+        struct [<libnss_module_ $mod_ident _network>];
+
+        impl $crate::network::NetworkHooks for [<libnss_module_ $mod_ident _network>] {
+            fn get_all_entries() -> $crate::interop::Response<Vec<$crate::network::Network>> {
+                <$module as $crate::module::NssModule>::get_all_networks(*[<MODULE_ $mod_ident _INSTANCE>])
+            }
+
+            fn open_enumeration() -> $crate::interop::Response<$crate::interop::Enumeration<$crate::network::Network>> {
+                <$module as $crate::module::NssModule>::open_network_enumeration(*[<MODULE_ $mod_ident _INSTANCE>])
+            }
+
+            fn enumeration_mode() -> $crate::interop::EnumerationMode {
+                <$module as $crate::module::NssModule>::network_enumeration_mode(*[<MODULE_ $mod_ident _INSTANCE>])
+            }
+
+            fn get_entry_by_name(name: String) -> $crate::interop::Response<$crate::network::Network> {
+                <$module as $crate::module::NssModule>::get_network_by_name(*[<MODULE_ $mod_ident _INSTANCE>], name)
+            }
+
+            fn get_entry_by_addr(net: u32) -> $crate::interop::Response<$crate::network::Network> {
+                <$module as $crate::module::NssModule>::get_network_by_addr(*[<MODULE_ $mod_ident _INSTANCE>], net)
+            }
+        }
+
+        $crate::libnss_network_hooks!($mod_ident, [<libnss_module_ $mod_ident _network>]);
+
+        #[allow(non_camel_case_types)]
+        struct [<libnss_module_ $mod_ident _ethers>];
+
+        impl $crate::ethers::EthersHooks for [<libnss_module_ $mod_ident _ethers>] {
+            fn get_all_entries() -> $crate::interop::Response<Vec<$crate::ethers::Ether>> {
+                <$module as $crate::module::NssModule>::get_all_ethers(*[<MODULE_ $mod_ident _INSTANCE>])
+            }
+
+            fn open_enumeration() -> $crate::interop::Response<$crate::interop::Enumeration<$crate::ethers::Ether>> {
+                <$module as $crate::module::NssModule>::open_ethers_enumeration(*[<MODULE_ $mod_ident _INSTANCE>])
+            }
+
+            fn enumeration_mode() -> $crate::interop::EnumerationMode {
+                <$module as $crate::module::NssModule>::ethers_enumeration_mode(*[<MODULE_ $mod_ident _INSTANCE>])
+            }
+
+            fn get_entry_by_name(name: String) -> $crate::interop::Response<$crate::ethers::Ether> {
+                <$module as $crate::module::NssModule>::get_ether_by_name(*[<MODULE_ $mod_ident _INSTANCE>], name)
+            }
+
+            fn get_entry_by_addr(addr: [u8; 6]) -> $crate::interop::Response<$crate::ethers::Ether> {
+                <$module as $crate::module::NssModule>::get_ether_by_addr(*[<MODULE_ $mod_ident _INSTANCE>], addr)
+            }
+        }
+
+        $crate::libnss_ethers_hooks!($mod_ident, [<libnss_module_ $mod_ident _ethers>]);
+
+        #[allow(non_camel_case_types)]
+        struct [<libnss_module_ $mod_ident _rpc>];
+
+        impl $crate::rpc::RpcHooks for [<libnss_module_ $mod_ident _rpc>] {
+            fn get_all_entries() -> $crate::interop::Response<Vec<$crate::rpc::Rpc>> {
+                <$module as $crate::module::NssModule>::get_all_rpcs(*[<MODULE_ $mod_ident _INSTANCE>])
+            }
+
+            fn open_enumeration() -> $crate::interop::Response<$crate::interop::Enumeration<$crate::rpc::Rpc>> {
+                <$module as $crate::module::NssModule>::open_rpc_enumeration(*[<MODULE_ $mod_ident _INSTANCE>])
+            }
+
+            fn enumeration_mode() -> $crate::interop::EnumerationMode {
+                <$module as $crate::module::NssModule>::rpc_enumeration_mode(*[<MODULE_ $mod_ident _INSTANCE>])
+            }
+
+            fn get_entry_by_name(name: String) -> $crate::interop::Response<$crate::rpc::Rpc> {
+                <$module as $crate::module::NssModule>::get_rpc_by_name(*[<MODULE_ $mod_ident _INSTANCE>], name)
+            }
+
+            fn get_entry_by_number(number: i32) -> $crate::interop::Response<$crate::rpc::Rpc> {
+                <$module as $crate::module::NssModule>::get_rpc_by_number(*[<MODULE_ $mod_ident _INSTANCE>], number)
+            }
+        }
+
+        $crate::libnss_rpc_hooks!($mod_ident, [<libnss_module_ $mod_ident _rpc>]);
+
+        #[allow(non_camel_case_types)]
+        struct [<libnss_module_ $mod_ident _netgroup>];
+
+        impl $crate::netgroup::NetgroupHooks for [<libnss_module_ $mod_ident _netgroup>] {
+            fn get_entries_by_name(netgroup: String) -> $crate::interop::Response<Vec<$crate::netgroup::NetgroupEntry>> {
+                <$module as $crate::module::NssModule>::get_netgroup_entries(*[<MODULE_ $mod_ident _INSTANCE>], netgroup)
+            }
+        }
+
+        $crate::libnss_netgroup_hooks!($mod_ident, [<libnss_module_ $mod_ident _netgroup>]);
+
+        #[allow(non_camel_case_types)]
+        struct [<libnss_module_ $mod_ident _alias>];
+
+        impl $crate::alias::AliasHooks for [<libnss_module_ $mod_ident _alias>] {
+            fn get_all_entries() -> $crate::interop::Response<Vec<$crate::alias::Alias>> {
+                <$module as $crate::module::NssModule>::get_all_aliases(*[<MODULE_ $mod_ident _INSTANCE>])
+            }
+
+            fn open_enumeration() -> $crate::interop::Response<$crate::interop::Enumeration<$crate::alias::Alias>> {
+                <$module as $crate::module::NssModule>::open_alias_enumeration(*[<MODULE_ $mod_ident _INSTANCE>])
+            }
+
+            fn enumeration_mode() -> $crate::interop::EnumerationMode {
+                <$module as $crate::module::NssModule>::alias_enumeration_mode(*[<MODULE_ $mod_ident _INSTANCE>])
+            }
+
+            fn get_entry_by_name(name: String) -> $crate::interop::Response<$crate::alias::Alias> {
+                <$module as $crate::module::NssModule>::get_alias_by_name(*[<MODULE_ $mod_ident _INSTANCE>], name)
+            }
+        }
+
+        $crate::libnss_alias_hooks!($mod_ident, [<libnss_module_ $mod_ident _alias>]);
+    }
+);
+($mod_ident:ident, $module:ty) => (
+    $crate::libnss_module!($mod_ident, $module = <$module as Default>::default());
+);
+}
@@ -0,0 +1,211 @@
+//! Netgroup database
+//!
+//! glibc implements `innetgr(3)` on top of `setnetgrent`, `getnetgrent_r` and `endnetgrent` and
+//! never looks up an `innetgr` function in the module, so no fast-path symbol is exported. Nested
+//! groups returned as `NetgroupEntry::Group` are resolved by glibc itself.
+use crate::interop::{CBuffer, Iterator, Response, ToC, ToCError};
+
+#[derive(Clone)]
+pub struct NetgroupTriple {
+    /// `None` matches any host
+    pub host: Option<String>,
+    /// `None` matches any user
+    pub user: Option<String>,
+    /// `None` matches any domain
+    pub domain: Option<String>,
+}
+
+#[derive(Clone)]
+pub enum NetgroupEntry {
+    Triple(NetgroupTriple),
+    /// Name of a nested netgroup
+    Group(String),
+}
+
+unsafe fn write_opt_str(
+    buffer: &mut CBuffer,
+    string: &Option<String>,
+) -> Result<*const libc::c_char, ToCError> {
+    match string {
+        Some(s) => Ok(buffer.write_str(s)?),
+        None => Ok(std::ptr::null()),
+    }
+}
+
+impl ToC<CNetgrent> for NetgroupEntry {
+    unsafe fn to_c(&self, result: *mut CNetgrent, buffer: &mut CBuffer) -> Result<(), ToCError> {
+        match self {
+            NetgroupEntry::Triple(triple) => {
+                (*result).type_ = CNetgrentType::Triple as libc::c_int;
+                (*result).val.triple = CNetgrentTriple {
+                    host: write_opt_str(buffer, &triple.host)?,
+                    user: write_opt_str(buffer, &triple.user)?,
+                    domain: write_opt_str(buffer, &triple.domain)?,
+                };
+            }
+            NetgroupEntry::Group(group) => {
+                (*result).type_ = CNetgrentType::Group as libc::c_int;
+                (*result).val.group = buffer.write_str(group)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+pub trait NetgroupHooks {
+    fn get_entries_by_name(netgroup: String) -> Response<Vec<NetgroupEntry>>;
+}
+
+pub enum CNetgrentType {
+    Triple = 0,
+    Group = 1,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct CNetgrentTriple {
+    pub host: *const libc::c_char,
+    pub user: *const libc::c_char,
+    pub domain: *const libc::c_char,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub union CNetgrentVal {
+    pub triple: CNetgrentTriple,
+    pub group: *const libc::c_char,
+}
+
+/// NSS C netgroup state, shared between glibc and the module across calls
+/// https://code.woboq.org/userspace/glibc/nss/netgroup.h.html
+///
+/// `data` holds the module's `Iterator<NetgroupEntry>` between `setnetgrent` and `endnetgrent`.
+#[repr(C)]
+#[allow(missing_copy_implementations)]
+pub struct CNetgrent {
+    pub type_: libc::c_int,
+    pub val: CNetgrentVal,
+    pub data: *mut libc::c_char,
+    pub data_size: libc::size_t,
+    pub position: libc::c_ulong,
+    pub first: libc::c_int,
+    pub known_groups: *mut libc::c_void,
+    pub needed_groups: *mut libc::c_void,
+    pub nip: *mut libc::c_void,
+}
+
+impl CNetgrent {
+    /// Replaces the enumeration state stored in `data`, dropping any previous state
+    ///
+    /// # Safety
+    ///
+    /// `data` must be null or hold the state stored by `open`, as is the case for the `__netgrent`
+    /// passed by glibc.
+    pub unsafe fn open(&mut self, entries: Vec<NetgroupEntry>) {
+        self.close();
+
+        let mut iter = Iterator::<NetgroupEntry>::new();
+        iter.open(entries);
+
+        self.data = Box::into_raw(Box::new(iter)) as *mut libc::c_char;
+        self.data_size = std::mem::size_of::<Iterator<NetgroupEntry>>();
+        self.position = 0;
+    }
+
+    /// # Safety
+    ///
+    /// `data` must be null or hold the state stored by `open`, as is the case for the `__netgrent`
+    /// passed by glibc.
+    pub unsafe fn iterator(&mut self) -> Option<&mut Iterator<NetgroupEntry>> {
+        (self.data as *mut Iterator<NetgroupEntry>).as_mut()
+    }
+
+    /// Drops the enumeration state, leaving `data` null as glibc expects
+    ///
+    /// # Safety
+    ///
+    /// `data` must be null or hold the state stored by `open`, as is the case for the `__netgrent`
+    /// passed by glibc.
+    pub unsafe fn close(&mut self) {
+        if !self.data.is_null() {
+            drop(Box::from_raw(self.data as *mut Iterator<NetgroupEntry>));
+        }
+
+        self.data = std::ptr::null_mut();
+        self.data_size = 0;
+        self.position = 0;
+    }
+}
+
+#[macro_export]
+macro_rules! libnss_netgroup_hooks {
+($mod_ident:ident, $hooks_ident:ident) => (
+    $crate::_macro_internal::paste! {
+        pub use self::[<libnss_netgroup_ $mod_ident _hooks_impl>]::*;
+        mod [<libnss_netgroup_ $mod_ident _hooks_impl>] {
+            #![allow(non_upper_case_globals)]
+
+            use libc::c_int;
+            use std::ffi::CStr;
+            use std::str;
+            use $crate::interop::{Response, NssStatus};
+            use $crate::netgroup::{CNetgrent, NetgroupHooks};
+
+            #[no_mangle]
+            unsafe extern "C" fn [<_nss_ $mod_ident _setnetgrent>](
+                netgroup_: *const libc::c_char,
+                result: *mut CNetgrent
+            ) -> c_int {
+                $crate::interop::catch_panic_or(|| {
+                    let cstr = CStr::from_ptr(netgroup_);
+
+                    let response = match str::from_utf8(cstr.to_bytes()) {
+                        Ok(netgroup) => <super::$hooks_ident as NetgroupHooks>::get_entries_by_name(netgroup.to_string()),
+                        Err(_) => Response::NotFound
+                    };
+
+                    match response {
+                        Response::Success(entries) => {
+                            (*result).open(entries);
+                            NssStatus::Success as c_int
+                        }
+                        response => response.to_status() as c_int
+                    }
+                }, || NssStatus::Unavail as c_int)
+            }
+
+            #[no_mangle]
+            unsafe extern "C" fn [<_nss_ $mod_ident _endnetgrent>](result: *mut CNetgrent) -> c_int {
+                $crate::interop::catch_panic_or(|| {
+                    (*result).close();
+                    NssStatus::Success as c_int
+                }, || NssStatus::Unavail as c_int)
+            }
+
+            #[no_mangle]
+            unsafe extern "C" fn [<_nss_ $mod_ident _getnetgrent_r>](
+                result: *mut CNetgrent,
+                buf: *mut libc::c_char,
+                buflen: libc::size_t,
+                errnop: *mut c_int
+            ) -> c_int {
+                $crate::interop::catch_panic(errnop, || {
+                    let iter = match (*result).iterator() {
+                        Some(iter) => iter,
+                        None => {
+                            *errnop = libc::ENOENT;
+                            return NssStatus::Unavail as c_int;
+                        }
+                    };
+
+                    let code: c_int = iter.next().to_c(result, buf, buflen, errnop) as c_int;
+                    if code == NssStatus::TryAgain as c_int && *errnop == libc::ERANGE {
+                        iter.previous();
+                    }
+                    code
+                })
+            }
+        }
+    }
+)
+}
@@ -0,0 +1,173 @@
+use crate::interop::{enumerate, CBuffer, Enumeration, EnumerationMode, Response, ToC, ToCError};
+
+#[derive(Clone)]
+pub struct Network {
+    pub name: String,
+    pub aliases: Vec<String>,
+    /// Network number in host byte order, as returned by `inet_network(3)`
+    pub net: u32,
+}
+
+impl ToC<CNetent> for Network {
+    unsafe fn to_c(&self, result: *mut CNetent, buffer: &mut CBuffer) -> Result<(), ToCError> {
+        (*result).name = buffer.write_str(&self.name)?;
+        (*result).aliases = buffer.write_strs(&self.aliases)?;
+        (*result).addrtype = libc::AF_INET;
+        (*result).net = self.net;
+        Ok(())
+    }
+}
+
+pub trait NetworkHooks {
+    fn get_all_entries() -> Response<Vec<Network>>;
+
+    /// See `Enumeration`
+    fn open_enumeration() -> Response<Enumeration<Network>> {
+        Self::get_all_entries().map(enumerate)
+    }
+
+    /// See `EnumerationMode`
+    fn enumeration_mode() -> EnumerationMode {
+        EnumerationMode::Process
+    }
+
+    fn get_entry_by_name(name: String) -> Response<Network>;
+
+    /// Lookup a network by number (in host byte order)
+    fn get_entry_by_addr(net: u32) -> Response<Network>;
+}
+
+/// NSS C Network object
+/// https://man7.org/linux/man-pages/man3/getnetent.3.html
+#[repr(C)]
+#[allow(missing_copy_implementations)]
+pub struct CNetent {
+    pub name: *mut libc::c_char,
+    pub aliases: *mut *mut libc::c_char,
+    pub addrtype: libc::c_int,
+    pub net: u32,
+}
+
+#[macro_export]
+macro_rules! libnss_network_hooks {
+($mod_ident:ident, $hooks_ident:ident) => (
+    $crate::_macro_internal::paste! {
+        pub use self::[<libnss_network_ $mod_ident _hooks_impl>]::*;
+        mod [<libnss_network_ $mod_ident _hooks_impl>] {
+            #![allow(non_upper_case_globals)]
+
+            use libc::c_int;
+            use std::cell::RefCell;
+            use std::ffi::CStr;
+            use std::str;
+            use $crate::interop::{CBuffer, EnumerationCursor, EnumerationState, Response, NssStatus};
+            use $crate::network::{CNetent, Network, NetworkHooks};
+
+            // https://code.woboq.org/userspace/glibc/resolv/netdb.h.html#62
+            enum Herrno {
+                NetDbInternal = -1,
+                NetDbSuccess = 0,
+                HostNotFound = 1,
+                TryAgain = 2,
+                NoRecovery = 3,
+            }
+
+            unsafe fn set_herrno(status: NssStatus, errnop: *mut c_int, h_errnop: *mut c_int) {
+                *h_errnop = match status {
+                    NssStatus::Success => Herrno::NetDbSuccess,
+                    // glibc only retries with a larger buffer when h_errno is NETDB_INTERNAL
+                    NssStatus::TryAgain if *errnop == libc::ERANGE => Herrno::NetDbInternal,
+                    NssStatus::TryAgain => Herrno::TryAgain,
+                    NssStatus::Unavail => Herrno::NoRecovery,
+                    NssStatus::NotFound => Herrno::HostNotFound,
+                    _ => Herrno::NetDbInternal,
+                } as c_int;
+            }
+
+            thread_local! {
+            static [<NETWORK_ $mod_ident _THREAD_ITERATOR>]: RefCell<EnumerationCursor<Network>> = RefCell::new(EnumerationCursor::new());
+            }
+
+            $crate::_macro_internal::lazy_static! {
+            static ref [<NETWORK_ $mod_ident _ITERATOR>]: EnumerationState<Network> = EnumerationState::new(
+                <super::$hooks_ident as NetworkHooks>::enumeration_mode(),
+                <super::$hooks_ident as NetworkHooks>::open_enumeration,
+                &[<NETWORK_ $mod_ident _THREAD_ITERATOR>]
+            );
+            }
+
+            #[no_mangle]
+            extern "C" fn [<_nss_ $mod_ident _setnetent>]() -> c_int {
+                $crate::interop::catch_panic_or(|| {
+                    [<NETWORK_ $mod_ident _ITERATOR>].set() as c_int
+                }, || NssStatus::Unavail as c_int)
+            }
+
+            #[no_mangle]
+            extern "C" fn [<_nss_ $mod_ident _endnetent>]() -> c_int {
+                $crate::interop::catch_panic_or(|| {
+                    [<NETWORK_ $mod_ident _ITERATOR>].end() as c_int
+                }, || NssStatus::Unavail as c_int)
+            }
+
+            #[no_mangle]
+            unsafe extern "C" fn [<_nss_ $mod_ident _getnetent_r>](
+                result: *mut CNetent,
+                buf: *mut libc::c_char,
+                buflen: libc::size_t,
+                errnop: *mut c_int,
+                h_errnop: *mut c_int
+            ) -> c_int {
+                $crate::interop::catch_panic_herrno(errnop, h_errnop, || {
+                    let status = [<NETWORK_ $mod_ident _ITERATOR>].get(errnop, |response| response.to_c(result, buf, buflen, errnop));
+                    set_herrno(status, errnop, h_errnop);
+                    status as c_int
+                })
+            }
+
+            #[no_mangle]
+            unsafe extern "C" fn [<_nss_ $mod_ident _getnetbyname_r>](
+                name_: *const libc::c_char,
+                result: *mut CNetent,
+                buf: *mut libc::c_char,
+                buflen: libc::size_t,
+                errnop: *mut c_int,
+                h_errnop: *mut c_int
+            ) -> c_int {
+                $crate::interop::catch_panic_herrno(errnop, h_errnop, || {
+                    let cstr = CStr::from_ptr(name_);
+
+                    let status = match str::from_utf8(cstr.to_bytes()) {
+                        Ok(name) => <super::$hooks_ident as NetworkHooks>::get_entry_by_name(name.to_string()),
+                        Err(_) => Response::NotFound
+                    }.to_c(result, buf, buflen, errnop);
+
+                    set_herrno(status, errnop, h_errnop);
+                    status as c_int
+                })
+            }
+
+            #[no_mangle]
+            unsafe extern "C" fn [<_nss_ $mod_ident _getnetbyaddr_r>](
+                net: u32,
+                type_: c_int,
+                result: *mut CNetent,
+                buf: *mut libc::c_char,
+                buflen: libc::size_t,
+                errnop: *mut c_int,
+                h_errnop: *mut c_int
+            ) -> c_int {
+                $crate::interop::catch_panic_herrno(errnop, h_errnop, || {
+                    let status = match type_ {
+                        libc::AF_INET => <super::$hooks_ident as NetworkHooks>::get_entry_by_addr(net),
+                        _ => Response::NotFound
+                    }.to_c(result, buf, buflen, errnop);
+
+                    set_herrno(status, errnop, h_errnop);
+                    status as c_int
+                })
+            }
+        }
+    }
+)
+}
@@ -1,4 +1,4 @@
-use crate::interop::{CBuffer, Response, ToC};
+use crate::interop::{enumerate, CBuffer, Enumeration, EnumerationMode, Response, ToC, ToCError};
 
 #[derive(Clone)]
 pub struct Passwd {
@@ -11,8 +11,18 @@ pub struct Passwd {
     pub shell: String,
 }
 
+impl Passwd {
+    /// Upper bound of the buffer space needed to return this entry
+    pub fn required_size(&self) -> usize {
+        [&self.name, &self.passwd, &self.gecos, &self.dir, &self.shell]
+            .iter()
+            .map(|s| CBuffer::str_size(s))
+            .sum()
+    }
+}
+
 impl ToC<CPasswd> for Passwd {
-    unsafe fn to_c(&self, result: *mut CPasswd, buffer: &mut CBuffer) -> std::io::Result<()> {
+    unsafe fn to_c(&self, result: *mut CPasswd, buffer: &mut CBuffer) -> Result<(), ToCError> {
         (*result).name = buffer.write_str(&self.name)?;
         (*result).passwd = buffer.write_str(&self.passwd)?;
         (*result).uid = self.uid as libc::uid_t;
@@ -22,16 +32,49 @@ impl ToC<CPasswd> for Passwd {
         (*result).shell = buffer.write_str(&self.shell)?;
         Ok(())
     }
+
+    fn required_size(&self) -> Option<usize> {
+        Some(Passwd::required_size(self))
+    }
 }
 
 pub trait PasswdHooks {
     fn get_all_entries() -> Response<Vec<Passwd>>;
 
+    /// See `Enumeration`
+    fn open_enumeration() -> Response<Enumeration<Passwd>> {
+        Self::get_all_entries().map(enumerate)
+    }
+
+    /// See `EnumerationMode`
+    fn enumeration_mode() -> EnumerationMode {
+        EnumerationMode::Process
+    }
+
     fn get_entry_by_uid(uid: libc::uid_t) -> Response<Passwd>;
 
     fn get_entry_by_name(name: String) -> Response<Passwd>;
 }
 
+/// Instance based variant of `PasswdHooks`, exported with `libnss_passwd_hooks!(name, Type = new)`
+pub trait PasswdBackend {
+    fn get_all_entries(&self) -> Response<Vec<Passwd>>;
+
+    /// See `Enumeration`
+    fn open_enumeration(&self) -> Response<Enumeration<Passwd>> {
+        self.get_all_entries().map(enumerate)
+    }
+
+    /// See `EnumerationMode`
+    fn enumeration_mode(&self) -> EnumerationMode {
+        EnumerationMode::Process
+    }
+
+    fn get_entry_by_uid(&self, uid: libc::uid_t) -> Response<Passwd>;
+
+    fn get_entry_by_name(&self, name: String) -> Response<Passwd>;
+}
+
 #[repr(C)]
 #[allow(missing_copy_implementations)]
 pub struct CPasswd {
@@ -61,32 +104,36 @@ macro_rules! libnss_passwd_hooks {
             #![allow(non_upper_case_globals)]
 
             use libc::c_int;
+            use std::cell::RefCell;
             use std::ffi::CStr;
             use std::str;
-            use std::sync::{Mutex, MutexGuard};
-            use $crate::interop::{CBuffer, Iterator, Response, NssStatus};
+            use $crate::interop::{CBuffer, EnumerationCursor, EnumerationState, Response, NssStatus};
             use $crate::passwd::{CPasswd, Passwd, PasswdHooks};
 
+            thread_local! {
+            static [<PASSWD_ $mod_ident _THREAD_ITERATOR>]: RefCell<EnumerationCursor<Passwd>> = RefCell::new(EnumerationCursor::new());
+            }
+
             $crate::_macro_internal::lazy_static! {
-            static ref [<PASSWD_ $mod_ident _ITERATOR>]: Mutex<Iterator<Passwd>> = Mutex::new(Iterator::<Passwd>::new());
+            static ref [<PASSWD_ $mod_ident _ITERATOR>]: EnumerationState<Passwd> = EnumerationState::new(
+                <super::$hooks_ident as PasswdHooks>::enumeration_mode(),
+                <super::$hooks_ident as PasswdHooks>::open_enumeration,
+                &[<PASSWD_ $mod_ident _THREAD_ITERATOR>]
+            );
             }
 
             #[no_mangle]
             extern "C" fn [<_nss_ $mod_ident _setpwent>]() -> c_int {
-                let mut iter: MutexGuard<Iterator<Passwd>> = [<PASSWD_ $mod_ident _ITERATOR>].lock().unwrap();
-
-                let status = match(<super::$hooks_ident as PasswdHooks>::get_all_entries()) {
-                    Response::Success(entries) => iter.open(entries),
-                    response => response.to_status()
-                };
-
-                status as c_int
+                $crate::interop::catch_panic_or(|| {
+                    [<PASSWD_ $mod_ident _ITERATOR>].set() as c_int
+                }, || NssStatus::Unavail as c_int)
             }
 
             #[no_mangle]
             extern "C" fn [<_nss_ $mod_ident _endpwent>]() -> c_int {
-                let mut iter: MutexGuard<Iterator<Passwd>> = [<PASSWD_ $mod_ident _ITERATOR>].lock().unwrap();
-                iter.close() as c_int
+                $crate::interop::catch_panic_or(|| {
+                    [<PASSWD_ $mod_ident _ITERATOR>].end() as c_int
+                }, || NssStatus::Unavail as c_int)
             }
 
             #[no_mangle]
@@ -96,12 +143,9 @@ macro_rules! libnss_passwd_hooks {
                 buflen: libc::size_t,
                 errnop: *mut c_int
             ) -> c_int {
-                let mut iter: MutexGuard<Iterator<Passwd>> = [<PASSWD_ $mod_ident _ITERATOR>].lock().unwrap();
-                let code: c_int = iter.next().to_c(result, buf, buflen, errnop) as c_int;
-                if code == NssStatus::TryAgain as c_int {
-                    iter.previous();
-                }
-                return code;
+                $crate::interop::catch_panic(errnop, || {
+                    [<PASSWD_ $mod_ident _ITERATOR>].get(errnop, |response| response.to_c(result, buf, buflen, errnop)) as c_int
+                })
             }
 
             #[no_mangle]
@@ -112,7 +156,9 @@ macro_rules! libnss_passwd_hooks {
                 buflen: libc::size_t,
                 errnop: *mut c_int
             ) -> c_int {
-                <super::$hooks_ident as PasswdHooks>::get_entry_by_uid(uid).to_c(result, buf, buflen, errnop) as c_int
+                $crate::interop::catch_panic(errnop, || {
+                    <super::$hooks_ident as PasswdHooks>::get_entry_by_uid(uid).to_c(result, buf, buflen, errnop) as c_int
+                })
             }
 
             #[no_mangle]
@@ -123,16 +169,52 @@ macro_rules! libnss_passwd_hooks {
                 buflen: libc::size_t,
                 errnop: *mut c_int
             ) -> c_int {
-                let cstr = CStr::from_ptr(name_);
+                $crate::interop::catch_panic(errnop, || {
+                    let cstr = CStr::from_ptr(name_);
+
+                    let response = match str::from_utf8(cstr.to_bytes()) {
+                        Ok(name) => <super::$hooks_ident as PasswdHooks>::get_entry_by_name(name.to_string()),
+                        Err(_) => Response::NotFound
+                    };
+
+                    response.to_c(result, buf, buflen, errnop) as c_int
+                })
+            }
+        }
+    }
+);
+($mod_ident:ident, $backend:ty = $new:expr) => (
+    $crate::_macro_internal::paste! {
+        $crate::_macro_internal::lazy_static! {
+        static ref [<PASSWD_ $mod_ident _INSTANCE>]: &'static $backend = $crate::interop::shared_instance(|| $new);
+        }
 
-                let response = match str::from_utf8(cstr.to_bytes()) {
-                    Ok(name) => <super::$hooks_ident as PasswdHooks>::get_entry_by_name(name.to_string()),
-                    Err(_) => Response::NotFound
-                };
+        #[allow(non_camel_case_types)]
+        struct [<libnss_passwd_ $mod_ident _instance>];
 
-                response.to_c(result, buf, buflen, errnop) as c_int
+        impl $crate::passwd::PasswdHooks for [<libnss_passwd_ $mod_ident _instance>] {
+            fn get_all_entries() -> $crate::interop::Response<Vec<$crate::passwd::Passwd>> {
+                <$backend as $crate::passwd::PasswdBackend>::get_all_entries(*[<PASSWD_ $mod_ident _INSTANCE>])
+            }
+
+            fn open_enumeration() -> $crate::interop::Response<$crate::interop::Enumeration<$crate::passwd::Passwd>> {
+                <$backend as $crate::passwd::PasswdBackend>::open_enumeration(*[<PASSWD_ $mod_ident _INSTANCE>])
+            }
+
+            fn enumeration_mode() -> $crate::interop::EnumerationMode {
+                <$backend as $crate::passwd::PasswdBackend>::enumeration_mode(*[<PASSWD_ $mod_ident _INSTANCE>])
+            }
+
+            fn get_entry_by_uid(uid: libc::uid_t) -> $crate::interop::Response<$crate::passwd::Passwd> {
+                <$backend as $crate::passwd::PasswdBackend>::get_entry_by_uid(*[<PASSWD_ $mod_ident _INSTANCE>], uid)
+            }
+
+            fn get_entry_by_name(name: String) -> $crate::interop::Response<$crate::passwd::Passwd> {
+                <$backend as $crate::passwd::PasswdBackend>::get_entry_by_name(*[<PASSWD_ $mod_ident _INSTANCE>], name)
             }
         }
+
+        $crate::libnss_passwd_hooks!($mod_ident, [<libnss_passwd_ $mod_ident _instance>]);
     }
-)
+);
 }
@@ -0,0 +1,133 @@
+use crate::interop::{enumerate, CBuffer, Enumeration, EnumerationMode, Response, ToC, ToCError};
+
+#[derive(Clone)]
+pub struct Rpc {
+    pub name: String,
+    pub aliases: Vec<String>,
+    pub number: i32,
+}
+
+impl ToC<CRpcent> for Rpc {
+    unsafe fn to_c(&self, result: *mut CRpcent, buffer: &mut CBuffer) -> Result<(), ToCError> {
+        (*result).name = buffer.write_str(&self.name)?;
+        (*result).aliases = buffer.write_strs(&self.aliases)?;
+        (*result).number = self.number as libc::c_int;
+        Ok(())
+    }
+}
+
+pub trait RpcHooks {
+    fn get_all_entries() -> Response<Vec<Rpc>>;
+
+    /// See `Enumeration`
+    fn open_enumeration() -> Response<Enumeration<Rpc>> {
+        Self::get_all_entries().map(enumerate)
+    }
+
+    /// See `EnumerationMode`
+    fn enumeration_mode() -> EnumerationMode {
+        EnumerationMode::Process
+    }
+
+    fn get_entry_by_name(name: String) -> Response<Rpc>;
+
+    fn get_entry_by_number(number: i32) -> Response<Rpc>;
+}
+
+/// NSS C RPC object
+/// https://man7.org/linux/man-pages/man3/getrpcent.3.html
+#[repr(C)]
+#[allow(missing_copy_implementations)]
+pub struct CRpcent {
+    pub name: *mut libc::c_char,
+    pub aliases: *mut *mut libc::c_char,
+    pub number: libc::c_int,
+}
+
+#[macro_export]
+macro_rules! libnss_rpc_hooks {
+($mod_ident:ident, $hooks_ident:ident) => (
+    $crate::_macro_internal::paste! {
+        pub use self::[<libnss_rpc_ $mod_ident _hooks_impl>]::*;
+        mod [<libnss_rpc_ $mod_ident _hooks_impl>] {
+            #![allow(non_upper_case_globals)]
+
+            use libc::c_int;
+            use std::cell::RefCell;
+            use std::ffi::CStr;
+            use std::str;
+            use $crate::interop::{CBuffer, EnumerationCursor, EnumerationState, Response, NssStatus};
+            use $crate::rpc::{CRpcent, Rpc, RpcHooks};
+
+            thread_local! {
+            static [<RPC_ $mod_ident _THREAD_ITERATOR>]: RefCell<EnumerationCursor<Rpc>> = RefCell::new(EnumerationCursor::new());
+            }
+
+            $crate::_macro_internal::lazy_static! {
+            static ref [<RPC_ $mod_ident _ITERATOR>]: EnumerationState<Rpc> = EnumerationState::new(
+                <super::$hooks_ident as RpcHooks>::enumeration_mode(),
+                <super::$hooks_ident as RpcHooks>::open_enumeration,
+                &[<RPC_ $mod_ident _THREAD_ITERATOR>]
+            );
+            }
+
+            #[no_mangle]
+            extern "C" fn [<_nss_ $mod_ident _setrpcent>]() -> c_int {
+                $crate::interop::catch_panic_or(|| {
+                    [<RPC_ $mod_ident _ITERATOR>].set() as c_int
+                }, || NssStatus::Unavail as c_int)
+            }
+
+            #[no_mangle]
+            extern "C" fn [<_nss_ $mod_ident _endrpcent>]() -> c_int {
+                $crate::interop::catch_panic_or(|| {
+                    [<RPC_ $mod_ident _ITERATOR>].end() as c_int
+                }, || NssStatus::Unavail as c_int)
+            }
+
+            #[no_mangle]
+            unsafe extern "C" fn [<_nss_ $mod_ident _getrpcent_r>](
+                result: *mut CRpcent,
+                buf: *mut libc::c_char,
+                buflen: libc::size_t,
+                errnop: *mut c_int
+            ) -> c_int {
+                $crate::interop::catch_panic(errnop, || {
+                    [<RPC_ $mod_ident _ITERATOR>].get(errnop, |response| response.to_c(result, buf, buflen, errnop)) as c_int
+                })
+            }
+
+            #[no_mangle]
+            unsafe extern "C" fn [<_nss_ $mod_ident _getrpcbyname_r>](
+                name_: *const libc::c_char,
+                result: *mut CRpcent,
+                buf: *mut libc::c_char,
+                buflen: libc::size_t,
+                errnop: *mut c_int
+            ) -> c_int {
+                $crate::interop::catch_panic(errnop, || {
+                    let cstr = CStr::from_ptr(name_);
+
+                    match str::from_utf8(cstr.to_bytes()) {
+                        Ok(name) => <super::$hooks_ident as RpcHooks>::get_entry_by_name(name.to_string()),
+                        Err(_) => Response::NotFound
+                    }.to_c(result, buf, buflen, errnop) as c_int
+                })
+            }
+
+            #[no_mangle]
+            unsafe extern "C" fn [<_nss_ $mod_ident _getrpcbynumber_r>](
+                number: c_int,
+                result: *mut CRpcent,
+                buf: *mut libc::c_char,
+                buflen: libc::size_t,
+                errnop: *mut c_int
+            ) -> c_int {
+                $crate::interop::catch_panic(errnop, || {
+                    <super::$hooks_ident as RpcHooks>::get_entry_by_number(number).to_c(result, buf, buflen, errnop) as c_int
+                })
+            }
+        }
+    }
+)
+}
@@ -0,0 +1,162 @@
+use crate::interop::{enumerate, CBuffer, Enumeration, EnumerationMode, Response, ToC, ToCError};
+
+#[derive(Clone)]
+pub struct Service {
+    pub name: String,
+    pub aliases: Vec<String>,
+    pub port: u16,
+    pub proto: String,
+}
+
+impl ToC<CServent> for Service {
+    unsafe fn to_c(&self, result: *mut CServent, buffer: &mut CBuffer) -> Result<(), ToCError> {
+        (*result).name = buffer.write_str(&self.name)?;
+        (*result).aliases = buffer.write_strs(&self.aliases)?;
+        // s_port is stored in network byte order
+        (*result).port = self.port.to_be() as libc::c_int;
+        (*result).proto = buffer.write_str(&self.proto)?;
+        Ok(())
+    }
+}
+
+pub trait ServiceHooks {
+    fn get_all_entries() -> Response<Vec<Service>>;
+
+    /// See `Enumeration`
+    fn open_enumeration() -> Response<Enumeration<Service>> {
+        Self::get_all_entries().map(enumerate)
+    }
+
+    /// See `EnumerationMode`
+    fn enumeration_mode() -> EnumerationMode {
+        EnumerationMode::Process
+    }
+
+    /// Lookup a service by name, `proto` is `None` when any protocol may match
+    fn get_entry_by_name(name: String, proto: Option<String>) -> Response<Service>;
+
+    /// Lookup a service by port (in host byte order), `proto` is `None` when any protocol may match
+    fn get_entry_by_port(port: u16, proto: Option<String>) -> Response<Service>;
+}
+
+/// NSS C Service object
+/// https://man7.org/linux/man-pages/man3/getservent.3.html
+#[repr(C)]
+#[allow(missing_copy_implementations)]
+pub struct CServent {
+    pub name: *mut libc::c_char,
+    pub aliases: *mut *mut libc::c_char,
+    pub port: libc::c_int,
+    pub proto: *mut libc::c_char,
+}
+
+#[macro_export]
+macro_rules! libnss_service_hooks {
+($mod_ident:ident, $hooks_ident:ident) => (
+    $crate::_macro_internal::paste! {
+        pub use self::[<libnss_service_ $mod_ident _hooks_impl>]::*;
+        mod [<libnss_service_ $mod_ident _hooks_impl>] {
+            #![allow(non_upper_case_globals)]
+
+            use libc::c_int;
+            use std::cell::RefCell;
+            use std::ffi::CStr;
+            use std::str;
+            use $crate::interop::{CBuffer, EnumerationCursor, EnumerationState, Response, NssStatus};
+            use $crate::service::{CServent, Service, ServiceHooks};
+
+            thread_local! {
+            static [<SERVICE_ $mod_ident _THREAD_ITERATOR>]: RefCell<EnumerationCursor<Service>> = RefCell::new(EnumerationCursor::new());
+            }
+
+            $crate::_macro_internal::lazy_static! {
+            static ref [<SERVICE_ $mod_ident _ITERATOR>]: EnumerationState<Service> = EnumerationState::new(
+                <super::$hooks_ident as ServiceHooks>::enumeration_mode(),
+                <super::$hooks_ident as ServiceHooks>::open_enumeration,
+                &[<SERVICE_ $mod_ident _THREAD_ITERATOR>]
+            );
+            }
+
+            unsafe fn proto_from_ptr(proto_: *const libc::c_char) -> Result<Option<String>, ()> {
+                if proto_.is_null() {
+                    return Ok(None);
+                }
+
+                match str::from_utf8(CStr::from_ptr(proto_).to_bytes()) {
+                    Ok(proto) => Ok(Some(proto.to_string())),
+                    Err(_) => Err(()),
+                }
+            }
+
+            #[no_mangle]
+            extern "C" fn [<_nss_ $mod_ident _setservent>]() -> c_int {
+                $crate::interop::catch_panic_or(|| {
+                    [<SERVICE_ $mod_ident _ITERATOR>].set() as c_int
+                }, || NssStatus::Unavail as c_int)
+            }
+
+            #[no_mangle]
+            extern "C" fn [<_nss_ $mod_ident _endservent>]() -> c_int {
+                $crate::interop::catch_panic_or(|| {
+                    [<SERVICE_ $mod_ident _ITERATOR>].end() as c_int
+                }, || NssStatus::Unavail as c_int)
+            }
+
+            #[no_mangle]
+            unsafe extern "C" fn [<_nss_ $mod_ident _getservent_r>](
+                result: *mut CServent,
+                buf: *mut libc::c_char,
+                buflen: libc::size_t,
+                errnop: *mut c_int
+            ) -> c_int {
+                $crate::interop::catch_panic(errnop, || {
+                    [<SERVICE_ $mod_ident _ITERATOR>].get(errnop, |response| response.to_c(result, buf, buflen, errnop)) as c_int
+                })
+            }
+
+            #[no_mangle]
+            unsafe extern "C" fn [<_nss_ $mod_ident _getservbyname_r>](
+                name_: *const libc::c_char,
+                proto_: *const libc::c_char,
+                result: *mut CServent,
+                buf: *mut libc::c_char,
+                buflen: libc::size_t,
+                errnop: *mut c_int
+            ) -> c_int {
+                $crate::interop::catch_panic(errnop, || {
+                    let cstr = CStr::from_ptr(name_);
+
+                    let response = match (str::from_utf8(cstr.to_bytes()), proto_from_ptr(proto_)) {
+                        (Ok(name), Ok(proto)) => <super::$hooks_ident as ServiceHooks>::get_entry_by_name(name.to_string(), proto),
+                        _ => Response::NotFound
+                    };
+
+                    response.to_c(result, buf, buflen, errnop) as c_int
+                })
+            }
+
+            #[no_mangle]
+            unsafe extern "C" fn [<_nss_ $mod_ident _getservbyport_r>](
+                port: c_int,
+                proto_: *const libc::c_char,
+                result: *mut CServent,
+                buf: *mut libc::c_char,
+                buflen: libc::size_t,
+                errnop: *mut c_int
+            ) -> c_int {
+                $crate::interop::catch_panic(errnop, || {
+                    // The port is passed in network byte order
+                    let port = u16::from_be(port as u16);
+
+                    let response = match proto_from_ptr(proto_) {
+                        Ok(proto) => <super::$hooks_ident as ServiceHooks>::get_entry_by_port(port, proto),
+                        Err(_) => Response::NotFound
+                    };
+
+                    response.to_c(result, buf, buflen, errnop) as c_int
+                })
+            }
+        }
+    }
+)
+}
@@ -1,4 +1,4 @@
-use crate::interop::{CBuffer, Response, ToC};
+use crate::interop::{enumerate, CBuffer, Enumeration, EnumerationMode, Response, ToC, ToCError};
 #[derive(Clone)]
 pub struct Shadow {
     pub name: String,
@@ -12,8 +12,15 @@ pub struct Shadow {
     pub reserved: usize,
 }
 
+impl Shadow {
+    /// Upper bound of the buffer space needed to return this entry
+    pub fn required_size(&self) -> usize {
+        CBuffer::str_size(&self.name) + CBuffer::str_size(&self.passwd)
+    }
+}
+
 impl ToC<CShadow> for Shadow {
-    unsafe fn to_c(&self, result: *mut CShadow, buffer: &mut CBuffer) -> std::io::Result<()> {
+    unsafe fn to_c(&self, result: *mut CShadow, buffer: &mut CBuffer) -> Result<(), ToCError> {
         (*result).name = buffer.write_str(&self.name)?;
         (*result).passwd = buffer.write_str(&self.passwd)?;
         (*result).last_change = self.last_change as libc::c_long;
@@ -25,14 +32,45 @@ impl ToC<CShadow> for Shadow {
         (*result).reserved = self.reserved as libc::c_ulong;
         Ok(())
     }
+
+    fn required_size(&self) -> Option<usize> {
+        Some(Shadow::required_size(self))
+    }
 }
 
 pub trait ShadowHooks {
     fn get_all_entries() -> Response<Vec<Shadow>>;
 
+    /// See `Enumeration`
+    fn open_enumeration() -> Response<Enumeration<Shadow>> {
+        Self::get_all_entries().map(enumerate)
+    }
+
+    /// See `EnumerationMode`
+    fn enumeration_mode() -> EnumerationMode {
+        EnumerationMode::Process
+    }
+
     fn get_entry_by_name(name: String) -> Response<Shadow>;
 }
 
+/// Instance based variant of `ShadowHooks`, exported with `libnss_shadow_hooks!(name, Type = new)`
+pub trait ShadowBackend {
+    fn get_all_entries(&self) -> Response<Vec<Shadow>>;
+
+    /// See `Enumeration`
+    fn open_enumeration(&self) -> Response<Enumeration<Shadow>> {
+        self.get_all_entries().map(enumerate)
+    }
+
+    /// See `EnumerationMode`
+    fn enumeration_mode(&self) -> EnumerationMode {
+        EnumerationMode::Process
+    }
+
+    fn get_entry_by_name(&self, name: String) -> Response<Shadow>;
+}
+
 #[repr(C)]
 #[allow(missing_copy_implementations)]
 pub struct CShadow {
@@ -56,30 +94,36 @@ macro_rules! libnss_shadow_hooks {
             #![allow(non_upper_case_globals)]
 
             use libc::c_int;
+            use std::cell::RefCell;
             use std::ffi::CStr;
             use std::str;
-            use std::sync::{Mutex, MutexGuard};
-            use $crate::interop::{CBuffer, Iterator, Response, NssStatus};
+            use $crate::interop::{CBuffer, EnumerationCursor, EnumerationState, Response, NssStatus};
             use $crate::shadow::{CShadow, ShadowHooks, Shadow};
 
+            thread_local! {
+            static [<SHADOW_ $mod_ident _THREAD_ITERATOR>]: RefCell<EnumerationCursor<Shadow>> = RefCell::new(EnumerationCursor::new());
+            }
+
             $crate::_macro_internal::lazy_static! {
-            static ref [<SHADOW_ $mod_ident _ITERATOR>]: Mutex<Iterator<Shadow>> = Mutex::new(Iterator::<Shadow>::new());
+            static ref [<SHADOW_ $mod_ident _ITERATOR>]: EnumerationState<Shadow> = EnumerationState::new(
+                <super::$hooks_ident as ShadowHooks>::enumeration_mode(),
+                <super::$hooks_ident as ShadowHooks>::open_enumeration,
+                &[<SHADOW_ $mod_ident _THREAD_ITERATOR>]
+            );
             }
 
             #[no_mangle]
             extern "C" fn [<_nss_ $mod_ident _setspent>]() -> c_int {
-                let mut iter: MutexGuard<Iterator<Shadow>> = [<SHADOW_ $mod_ident _ITERATOR>].lock().unwrap();
-                let status = match(<super::$hooks_ident as ShadowHooks>::get_all_entries()) {
-                    Response::Success(entries) => iter.open(entries),
-                    response => response.to_status()
-                };
-                status as c_int
+                $crate::interop::catch_panic_or(|| {
+                    [<SHADOW_ $mod_ident _ITERATOR>].set() as c_int
+                }, || NssStatus::Unavail as c_int)
             }
 
             #[no_mangle]
             extern "C" fn [<_nss_ $mod_ident _endspent>]() -> c_int {
-                let mut iter: MutexGuard<Iterator<Shadow>> = [<SHADOW_ $mod_ident _ITERATOR>].lock().unwrap();
-                iter.close() as c_int
+                $crate::interop::catch_panic_or(|| {
+                    [<SHADOW_ $mod_ident _ITERATOR>].end() as c_int
+                }, || NssStatus::Unavail as c_int)
             }
 
             #[no_mangle]
@@ -89,12 +133,9 @@ macro_rules! libnss_shadow_hooks {
                 buflen: libc::size_t,
                 errnop: *mut c_int
             ) -> c_int {
-                let mut iter: MutexGuard<Iterator<Shadow>> = [<SHADOW_ $mod_ident _ITERATOR>].lock().unwrap();
-                let code: c_int = iter.next().to_c(result, buf, buflen, errnop) as c_int;
-                if code == NssStatus::TryAgain as c_int {
-                    iter.previous();
-                }
-                return code;
+                $crate::interop::catch_panic(errnop, || {
+                    [<SHADOW_ $mod_ident _ITERATOR>].get(errnop, |response| response.to_c(result, buf, buflen, errnop)) as c_int
+                })
             }
 
             #[no_mangle]
@@ -105,14 +146,46 @@ macro_rules! libnss_shadow_hooks {
                 buflen: libc::size_t,
                 errnop: *mut c_int
             ) -> c_int {
-                let cstr = CStr::from_ptr(name_);
+                $crate::interop::catch_panic(errnop, || {
+                    let cstr = CStr::from_ptr(name_);
+
+                    match str::from_utf8(cstr.to_bytes()) {
+                        Ok(name) => <super::$hooks_ident as ShadowHooks>::get_entry_by_name(name.to_string()),
+                        Err(_) => Response::NotFound
+                    }.to_c(result, buf, buflen, errnop) as c_int
+                })
+            }
+        }
+    }
+);
+($mod_ident:ident, $backend:ty = $new:expr) => (
+    $crate::_macro_internal::paste! {
+        $crate::_macro_internal::lazy_static! {
+        static ref [<SHADOW_ $mod_ident _INSTANCE>]: &'static $backend = $crate::interop::shared_instance(|| $new);
+        }
 
-                match str::from_utf8(cstr.to_bytes()) {
-                    Ok(name) => <super::$hooks_ident as ShadowHooks>::get_entry_by_name(name.to_string()),
-                    Err(_) => Response::NotFound
-                }.to_c(result, buf, buflen, errnop) as c_int
+        #[allow(non_camel_case_types)]
+        struct [<libnss_shadow_ $mod_ident _instance>];
+
+        impl $crate::shadow::ShadowHooks for [<libnss_shadow_ $mod_ident _instance>] {
+            fn get_all_entries() -> $crate::interop::Response<Vec<$crate::shadow::Shadow>> {
+                <$backend as $crate::shadow::ShadowBackend>::get_all_entries(*[<SHADOW_ $mod_ident _INSTANCE>])
+            }
+
+            fn open_enumeration() -> $crate::interop::Response<$crate::interop::Enumeration<$crate::shadow::Shadow>> {
+                <$backend as $crate::shadow::ShadowBackend>::open_enumeration(*[<SHADOW_ $mod_ident _INSTANCE>])
+            }
+
+            fn enumeration_mode() -> $crate::interop::EnumerationMode {
+                <$backend as $crate::shadow::ShadowBackend>::enumeration_mode(*[<SHADOW_ $mod_ident _INSTANCE>])
+            }
+
+            fn get_entry_by_name(name: String) -> $crate::interop::Response<$crate::shadow::Shadow> {
+                <$backend as $crate::shadow::ShadowBackend>::get_entry_by_name(*[<SHADOW_ $mod_ident _INSTANCE>], name)
             }
         }
+
+        $crate::libnss_shadow_hooks!($mod_ident, [<libnss_shadow_ $mod_ident _instance>]);
     }
-)
+);
 }
@@ -0,0 +1,202 @@
+//! Subordinate ID plugin for shadow-utils' libsubid
+//!
+//! Unlike the NSS databases, shadow-utils looks up the unprefixed `shadow_subid_*` symbols, so
+//! the library must be installed as `libsubid_<mod>.so` and enabled with `subid: <mod>` in
+//! `/etc/nsswitch.conf`. Only one subid module can be exported per library.
+use crate::interop::Response;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SubidRange {
+    pub start: u64,
+    pub count: u64,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SubidType {
+    Uid,
+    Gid,
+}
+
+impl SubidType {
+    pub fn from_c(id_type: libc::c_int) -> Option<Self> {
+        match id_type {
+            1 => Some(SubidType::Uid),
+            2 => Some(SubidType::Gid),
+            _ => None,
+        }
+    }
+}
+
+/// https://github.com/shadow-maint/shadow/blob/master/lib/subid.h
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SubidStatus {
+    Success = 0,
+    UnknownUser = 1,
+    ErrorConn = 2,
+    Error = 3,
+}
+
+impl SubidStatus {
+    pub fn from_response<R>(response: &Response<R>) -> Self {
+        match response {
+            Response::Success(..) => SubidStatus::Success,
+            Response::NotFound => SubidStatus::UnknownUser,
+            Response::TryAgain | Response::Unavail => SubidStatus::ErrorConn,
+            Response::Return => SubidStatus::Error,
+        }
+    }
+}
+
+pub trait SubidHooks {
+    fn get_ranges_by_owner(owner: String, id_type: SubidType) -> Response<Vec<SubidRange>>;
+
+    fn get_owners_by_id(id: u64, id_type: SubidType) -> Response<Vec<libc::uid_t>>;
+
+    fn has_range(owner: String, range: SubidRange, id_type: SubidType) -> Response<bool>;
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+pub struct CSubidRange {
+    pub start: libc::c_ulong,
+    pub count: libc::c_ulong,
+}
+
+/// Copies `items` into a `malloc`ed array which the caller releases with `free`
+///
+/// # Safety
+///
+/// `array` and `count` must be valid for writes.
+pub unsafe fn write_malloc_array<T: Copy>(
+    items: &[T],
+    array: *mut *mut T,
+    count: *mut libc::c_int,
+) -> SubidStatus {
+    *array = std::ptr::null_mut();
+    *count = 0;
+
+    if items.is_empty() {
+        return SubidStatus::Success;
+    }
+
+    let ptr = libc::malloc(std::mem::size_of_val(items)) as *mut T;
+    if ptr.is_null() {
+        return SubidStatus::Error;
+    }
+    std::ptr::copy_nonoverlapping(items.as_ptr(), ptr, items.len());
+
+    *array = ptr;
+    *count = items.len() as libc::c_int;
+    SubidStatus::Success
+}
+
+#[macro_export]
+macro_rules! libnss_subid_hooks {
+($mod_ident:ident, $hooks_ident:ident) => (
+    $crate::_macro_internal::paste! {
+        pub use self::[<libnss_subid_ $mod_ident _hooks_impl>]::*;
+        mod [<libnss_subid_ $mod_ident _hooks_impl>] {
+            #![allow(non_upper_case_globals)]
+
+            use libc::c_int;
+            use std::ffi::CStr;
+            use std::str;
+            use $crate::interop::Response;
+            use $crate::subid::{write_malloc_array, CSubidRange, SubidHooks, SubidRange, SubidStatus, SubidType};
+
+            #[no_mangle]
+            unsafe extern "C" fn shadow_subid_has_range(
+                owner_: *const libc::c_char,
+                start: libc::c_ulong,
+                count: libc::c_ulong,
+                id_type: c_int,
+                result: *mut bool
+            ) -> c_int {
+                $crate::interop::catch_panic_or(|| {
+                    *result = false;
+
+                    let id_type = match SubidType::from_c(id_type) {
+                        Some(id_type) => id_type,
+                        None => return SubidStatus::Error as c_int
+                    };
+
+                    let owner = match str::from_utf8(CStr::from_ptr(owner_).to_bytes()) {
+                        Ok(owner) => owner.to_string(),
+                        Err(_) => return SubidStatus::UnknownUser as c_int
+                    };
+
+                    let range = SubidRange { start: start as u64, count: count as u64 };
+                    let response = <super::$hooks_ident as SubidHooks>::has_range(owner, range, id_type);
+                    if let Response::Success(has_range) = response {
+                        *result = has_range;
+                    }
+
+                    SubidStatus::from_response(&response) as c_int
+                }, || SubidStatus::Error as c_int)
+            }
+
+            #[no_mangle]
+            unsafe extern "C" fn shadow_subid_list_owner_ranges(
+                owner_: *const libc::c_char,
+                id_type: c_int,
+                ranges: *mut *mut CSubidRange,
+                count: *mut c_int
+            ) -> c_int {
+                $crate::interop::catch_panic_or(|| {
+                    *ranges = std::ptr::null_mut();
+                    *count = 0;
+
+                    let id_type = match SubidType::from_c(id_type) {
+                        Some(id_type) => id_type,
+                        None => return SubidStatus::Error as c_int
+                    };
+
+                    let owner = match str::from_utf8(CStr::from_ptr(owner_).to_bytes()) {
+                        Ok(owner) => owner.to_string(),
+                        Err(_) => return SubidStatus::UnknownUser as c_int
+                    };
+
+                    match <super::$hooks_ident as SubidHooks>::get_ranges_by_owner(owner, id_type) {
+                        Response::Success(records) => {
+                            let records = records
+                                .iter()
+                                .map(|r| CSubidRange { start: r.start as libc::c_ulong, count: r.count as libc::c_ulong })
+                                .collect::<Vec<CSubidRange>>();
+                            write_malloc_array(&records, ranges, count) as c_int
+                        }
+                        response => SubidStatus::from_response(&response) as c_int
+                    }
+                }, || SubidStatus::Error as c_int)
+            }
+
+            #[no_mangle]
+            unsafe extern "C" fn shadow_subid_find_subid_owners(
+                id: libc::c_ulong,
+                id_type: c_int,
+                uids: *mut *mut libc::uid_t,
+                count: *mut c_int
+            ) -> c_int {
+                $crate::interop::catch_panic_or(|| {
+                    *uids = std::ptr::null_mut();
+                    *count = 0;
+
+                    let id_type = match SubidType::from_c(id_type) {
+                        Some(id_type) => id_type,
+                        None => return SubidStatus::Error as c_int
+                    };
+
+                    match <super::$hooks_ident as SubidHooks>::get_owners_by_id(id as u64, id_type) {
+                        Response::Success(records) => write_malloc_array(&records, uids, count) as c_int,
+                        response => SubidStatus::from_response(&response) as c_int
+                    }
+                }, || SubidStatus::Error as c_int)
+            }
+
+            #[no_mangle]
+            unsafe extern "C" fn shadow_subid_free(ptr: *mut libc::c_void) {
+                libc::free(ptr);
+            }
+        }
+    }
+)
+}
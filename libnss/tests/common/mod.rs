@@ -0,0 +1,51 @@
+//! Helpers shared by the integration tests
+
+// Not every test uses every helper
+#![allow(dead_code)]
+
+use libnss::interop::NssStatus;
+use libnss::passwd::{CPasswd, Passwd};
+use std::ffi::CStr;
+
+pub const SUCCESS: libc::c_int = NssStatus::Success as libc::c_int;
+pub const NOTFOUND: libc::c_int = NssStatus::NotFound as libc::c_int;
+pub const UNAVAIL: libc::c_int = NssStatus::Unavail as libc::c_int;
+pub const TRYAGAIN: libc::c_int = NssStatus::TryAgain as libc::c_int;
+
+pub fn passwd(name: &str, uid: u32) -> Passwd {
+    Passwd {
+        name: name.to_string(),
+        passwd: "x".to_string(),
+        uid,
+        gid: uid,
+        gecos: "".to_string(),
+        dir: "/".to_string(),
+        shell: "/bin/sh".to_string(),
+    }
+}
+
+/// Calls a `getpw*_r` entry point with `buflen` bytes of buffer, returning the status, errno and
+/// the name and uid of the returned entry
+pub unsafe fn getpw<F>(buflen: usize, call: F) -> (libc::c_int, libc::c_int, Option<(String, u32)>)
+where
+    F: FnOnce(*mut CPasswd, *mut libc::c_char, libc::size_t, *mut libc::c_int) -> libc::c_int,
+{
+    let mut result = std::mem::MaybeUninit::<CPasswd>::zeroed();
+    let mut buf = [0 as libc::c_char; 256];
+    let mut errno = 0;
+
+    let status = call(
+        result.as_mut_ptr(),
+        buf.as_mut_ptr(),
+        buflen.min(buf.len()),
+        &mut errno,
+    );
+    let entry = if status == SUCCESS {
+        let result = result.assume_init_ref();
+        let name = CStr::from_ptr(result.name).to_string_lossy().into_owned();
+        Some((name, result.uid))
+    } else {
+        None
+    };
+    (status, errno, entry)
+}
@@ -0,0 +1,153 @@
+//! Entries pulled by the generated `get*ent_r` entry points
+
+mod common;
+
+use common::{getpw, passwd, NOTFOUND, SUCCESS, TRYAGAIN};
+use libnss::interop::{
+    Enumeration, EnumerationCursor, EnumerationMode, EnumerationState, NssStatus, Response,
+};
+use libnss::passwd::Passwd;
+use std::cell::RefCell;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+/// Calls `get` like `getpwent_r` does, returning the status, errno and uid of the entry
+fn getpwent(
+    state: &EnumerationState<Passwd>,
+    buflen: usize,
+) -> (libc::c_int, libc::c_int, Option<u32>) {
+    let (status, errno, entry) = unsafe {
+        getpw(buflen, |result, buf, buflen, errnop| {
+            state.get(errnop, |response| {
+                response.to_c(result, buf, buflen, errnop)
+            }) as libc::c_int
+        })
+    };
+    (status, errno, entry.map(|(_, uid)| uid))
+}
+
+static FLAKY_PULLS: AtomicUsize = AtomicUsize::new(0);
+
+// The first page of entries fails to load
+fn open_flaky() -> Response<Enumeration<Passwd>> {
+    Response::Success(Box::new((0..3).map(|uid| {
+        FLAKY_PULLS.fetch_add(1, Ordering::SeqCst);
+        match uid {
+            0 => Response::TryAgain,
+            uid => Response::Success(passwd("user", uid)),
+        }
+    })))
+}
+
+thread_local! {
+    static FLAKY_THREAD: RefCell<EnumerationCursor<Passwd>> = RefCell::new(EnumerationCursor::new());
+    static SMALL_BUFFER_THREAD: RefCell<EnumerationCursor<Passwd>> = RefCell::new(EnumerationCursor::new());
+}
+
+#[test]
+fn try_again_from_the_enumeration_is_not_replayed() {
+    let state = EnumerationState::new(EnumerationMode::Process, open_flaky, &FLAKY_THREAD);
+    assert_eq!(state.set(), NssStatus::Success);
+
+    assert_eq!(getpwent(&state, 256), (TRYAGAIN, libc::EAGAIN, None));
+    assert_eq!(getpwent(&state, 256), (SUCCESS, 0, Some(1)));
+    assert_eq!(getpwent(&state, 256), (SUCCESS, 0, Some(2)));
+    assert_eq!(getpwent(&state, 256), (NOTFOUND, libc::ENOENT, None));
+    assert_eq!(FLAKY_PULLS.load(Ordering::SeqCst), 3);
+}
+
+fn open_passwd() -> Response<Enumeration<Passwd>> {
+    Response::Success(Box::new(
+        (0..2).map(|uid| Response::Success(passwd("user", uid))),
+    ))
+}
+
+#[test]
+fn entry_is_replayed_after_a_too_small_buffer() {
+    let state = EnumerationState::new(EnumerationMode::Process, open_passwd, &SMALL_BUFFER_THREAD);
+    assert_eq!(state.set(), NssStatus::Success);
+
+    assert_eq!(getpwent(&state, 8), (TRYAGAIN, libc::ERANGE, None));
+    assert_eq!(getpwent(&state, 256), (SUCCESS, 0, Some(0)));
+    assert_eq!(getpwent(&state, 256), (SUCCESS, 0, Some(1)));
+    assert_eq!(getpwent(&state, 256), (NOTFOUND, libc::ENOENT, None));
+    assert_eq!(state.end(), NssStatus::Success);
+}
+
+thread_local! {
+    static PER_THREAD: RefCell<EnumerationCursor<Passwd>> = RefCell::new(EnumerationCursor::new());
+    static SLOW_THREAD: RefCell<EnumerationCursor<Passwd>> = RefCell::new(EnumerationCursor::new());
+}
+
+fn uids(state: &EnumerationState<Passwd>, count: usize) -> Vec<Option<u32>> {
+    (0..count).map(|_| getpwent(state, 256).2).collect()
+}
+
+#[test]
+fn thread_mode_enumerates_independently() {
+    let state = EnumerationState::new(EnumerationMode::Thread, open_passwd, &PER_THREAD);
+
+    assert_eq!(state.set(), NssStatus::Success);
+    assert_eq!(uids(&state, 1), vec![Some(0)]);
+
+    thread::scope(|scope| {
+        // Another thread starts at the beginning, implicitly opening its own enumeration
+        scope.spawn(|| {
+            assert_eq!(uids(&state, 3), vec![Some(0), Some(1), None]);
+
+            // Ending it leaves the other threads untouched
+            assert_eq!(state.end(), NssStatus::Success);
+            assert_eq!(uids(&state, 1), vec![Some(0)]);
+        });
+    });
+
+    assert_eq!(uids(&state, 2), vec![Some(1), None]);
+    assert_eq!(state.end(), NssStatus::Success);
+}
+
+static SLOW_STARTED: AtomicBool = AtomicBool::new(false);
+static SLOW_RELEASED: AtomicBool = AtomicBool::new(false);
+
+// Pulling the first entry blocks until the test releases it
+fn open_slow() -> Response<Enumeration<Passwd>> {
+    Response::Success(Box::new((0..2).map(|uid| {
+        if uid == 0 {
+            SLOW_STARTED.store(true, Ordering::SeqCst);
+            while !SLOW_RELEASED.load(Ordering::SeqCst) {
+                thread::sleep(Duration::from_millis(1));
+            }
+        }
+        Response::Success(passwd("user", uid))
+    })))
+}
+
+#[test]
+fn slow_hook_does_not_block_set_and_end() {
+    let state = EnumerationState::new(EnumerationMode::Process, open_slow, &SLOW_THREAD);
+    assert_eq!(state.set(), NssStatus::Success);
+
+    thread::scope(|scope| {
+        let slow = scope.spawn(|| getpwent(&state, 256));
+        while !SLOW_STARTED.load(Ordering::SeqCst) {
+            thread::sleep(Duration::from_millis(1));
+        }
+
+        // set and end finish while the hook is still producing the entry
+        let (done, finished) = mpsc::channel();
+        let state = &state;
+        scope.spawn(move || {
+            let statuses = (state.end(), state.set(), state.end());
+            done.send(statuses).unwrap();
+        });
+        let statuses = finished.recv_timeout(Duration::from_secs(10));
+        SLOW_RELEASED.store(true, Ordering::SeqCst);
+
+        assert_eq!(
+            statuses,
+            Ok((NssStatus::Success, NssStatus::Success, NssStatus::Success))
+        );
+        assert_eq!(slow.join().unwrap(), (SUCCESS, 0, Some(0)));
+    });
+}
@@ -0,0 +1,475 @@
+//! The hosts entry points used by `gethostbyname*`, `gethostbyaddr` and `getaddrinfo`
+
+use libnss::host::{AddressFamily, Addresses, CGaihAddrtuple, CHost, Host, HostHooks};
+use libnss::interop::{NssStatus, Response};
+use libnss::libnss_host_hooks;
+use std::ffi::CStr;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+const V4: Ipv4Addr = Ipv4Addr::new(192, 0, 2, 42);
+const V6: Ipv6Addr = Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 0x42);
+const NAME_ONLY: Ipv4Addr = Ipv4Addr::new(192, 0, 2, 1);
+
+struct TestHosts;
+libnss_host_hooks!(hosts, TestHosts);
+
+impl HostHooks for TestHosts {
+    fn get_all_entries() -> Response<Vec<Host>> {
+        Response::Success(vec![])
+    }
+
+    fn get_host_by_name(name: &str, family: AddressFamily) -> Response<Host> {
+        match name {
+            "dual.example" => Response::Success(dual(family)),
+            _ => Response::NotFound,
+        }
+    }
+
+    fn get_host_by_addr(addr: IpAddr) -> Response<Host> {
+        match addr {
+            IpAddr::V4(addr) if addr == NAME_ONLY => Response::Success(Host {
+                name: "rev.example".to_string(),
+                aliases: vec![],
+                addresses: Addresses::V4(vec![]),
+                ttl: None,
+            }),
+            addr if addr == IpAddr::V4(V4) || addr == IpAddr::V6(V6) => {
+                Response::Success(dual(AddressFamily::Unspecified))
+            }
+            _ => Response::NotFound,
+        }
+    }
+}
+
+/// A host with addresses of both families, with a shorter TTL for the IPv6 lookup
+fn dual(family: AddressFamily) -> Host {
+    Host {
+        name: "canonical.example".to_string(),
+        aliases: vec!["dual.example".to_string()],
+        addresses: Addresses::Mixed(vec![IpAddr::V4(V4), IpAddr::V6(V6)]),
+        ttl: match family {
+            AddressFamily::IPv6 => Some(60),
+            _ => Some(300),
+        },
+    }
+}
+
+extern "C" {
+    fn _nss_hosts_gethostbyaddr_r(
+        addr: *const libc::c_char,
+        len: libc::size_t,
+        format: libc::c_int,
+        result: *mut CHost,
+        buf: *mut libc::c_char,
+        buflen: libc::size_t,
+        errnop: *mut libc::c_int,
+        h_errnop: *mut libc::c_int,
+    ) -> libc::c_int;
+    fn _nss_hosts_getcanonname_r(
+        name: *const libc::c_char,
+        buf: *mut libc::c_char,
+        buflen: libc::size_t,
+        result: *mut *mut libc::c_char,
+        errnop: *mut libc::c_int,
+        h_errnop: *mut libc::c_int,
+    ) -> libc::c_int;
+    fn _nss_hosts_gethostbyname2_r(
+        name: *const libc::c_char,
+        family: libc::c_int,
+        result: *mut CHost,
+        buf: *mut libc::c_char,
+        buflen: libc::size_t,
+        errnop: *mut libc::c_int,
+        h_errnop: *mut libc::c_int,
+    ) -> libc::c_int;
+    fn _nss_hosts_gethostbyname3_r(
+        name: *const libc::c_char,
+        family: libc::c_int,
+        result: *mut CHost,
+        buf: *mut libc::c_char,
+        buflen: libc::size_t,
+        errnop: *mut libc::c_int,
+        h_errnop: *mut libc::c_int,
+        ttlp: *mut i32,
+        canonp: *mut *const libc::c_char,
+    ) -> libc::c_int;
+    fn _nss_hosts_gethostbyname4_r(
+        name: *const libc::c_char,
+        pat: *mut *mut CGaihAddrtuple,
+        buf: *mut libc::c_char,
+        buflen: libc::size_t,
+        errnop: *mut libc::c_int,
+        h_errnop: *mut libc::c_int,
+        ttlp: *mut i32,
+    ) -> libc::c_int;
+}
+
+const SUCCESS: libc::c_int = NssStatus::Success as libc::c_int;
+
+/// Decoded `hostent`
+#[derive(Debug, PartialEq)]
+struct HostEnt {
+    name: String,
+    aliases: Vec<String>,
+    family: libc::c_int,
+    addresses: Vec<IpAddr>,
+}
+
+unsafe fn strs(mut list: *mut *mut libc::c_char) -> Vec<String> {
+    let mut strs = vec![];
+    while !(*list).is_null() {
+        strs.push(CStr::from_ptr(*list).to_string_lossy().into_owned());
+        list = list.add(1);
+    }
+    strs
+}
+
+unsafe fn decode(host: &CHost) -> HostEnt {
+    let mut addresses = vec![];
+    let mut list = host.h_addr_list;
+    while !(*list).is_null() {
+        addresses.push(match host.h_length {
+            4 => IpAddr::from(*(*list as *const [u8; 4])),
+            _ => IpAddr::from(*(*list as *const [u8; 16])),
+        });
+        list = list.add(1);
+    }
+
+    HostEnt {
+        name: CStr::from_ptr(host.name).to_string_lossy().into_owned(),
+        aliases: strs(host.h_aliases),
+        family: host.h_addrtype,
+        addresses,
+    }
+}
+
+/// Calls an entry point returning a `hostent`, returning its status, errno, h_errno and entry
+unsafe fn hostent<F>(
+    buflen: usize,
+    call: F,
+) -> (libc::c_int, libc::c_int, libc::c_int, Option<HostEnt>)
+where
+    F: FnOnce(
+        *mut CHost,
+        *mut libc::c_char,
+        libc::size_t,
+        *mut libc::c_int,
+        *mut libc::c_int,
+    ) -> libc::c_int,
+{
+    let mut result = std::mem::MaybeUninit::<CHost>::zeroed();
+    let mut buf = [0 as libc::c_char; 512];
+    let mut errno = 0;
+    let mut h_errno = 0;
+
+    let status = call(
+        result.as_mut_ptr(),
+        buf.as_mut_ptr(),
+        buflen,
+        &mut errno,
+        &mut h_errno,
+    );
+    let host = if status == SUCCESS {
+        Some(decode(result.assume_init_ref()))
+    } else {
+        None
+    };
+    (status, errno, h_errno, host)
+}
+
+unsafe fn gethostbyaddr(addr: IpAddr) -> (libc::c_int, libc::c_int, libc::c_int, Option<HostEnt>) {
+    let (octets, format) = match addr {
+        IpAddr::V4(addr) => (addr.octets().to_vec(), libc::AF_INET),
+        IpAddr::V6(addr) => (addr.octets().to_vec(), libc::AF_INET6),
+    };
+
+    hostent(512, |result, buf, buflen, errnop, h_errnop| {
+        _nss_hosts_gethostbyaddr_r(
+            octets.as_ptr() as *const libc::c_char,
+            octets.len(),
+            format,
+            result,
+            buf,
+            buflen,
+            errnop,
+            h_errnop,
+        )
+    })
+}
+
+#[test]
+fn reverse_lookup_returns_the_queried_address() {
+    unsafe {
+        // A backend which only knows the name still answers
+        let (status, _, h_errno, host) = gethostbyaddr(IpAddr::V4(NAME_ONLY));
+        assert_eq!((status, h_errno), (SUCCESS, 0));
+        assert_eq!(
+            host,
+            Some(HostEnt {
+                name: "rev.example".to_string(),
+                aliases: vec![],
+                family: libc::AF_INET,
+                addresses: vec![IpAddr::V4(NAME_ONLY)],
+            })
+        );
+
+        // A host with addresses of both families is returned in the queried family
+        for addr in [IpAddr::V4(V4), IpAddr::V6(V6)] {
+            let (status, _, _, host) = gethostbyaddr(addr);
+            let host = host.unwrap();
+            assert_eq!(status, SUCCESS);
+            assert_eq!(host.name, "canonical.example");
+            assert_eq!(host.addresses, vec![addr]);
+            assert_eq!(
+                host.family,
+                match addr {
+                    IpAddr::V4(_) => libc::AF_INET,
+                    IpAddr::V6(_) => libc::AF_INET6,
+                }
+            );
+        }
+
+        let (status, errno, h_errno, host) = gethostbyaddr(IpAddr::V4(Ipv4Addr::LOCALHOST));
+        assert_eq!(
+            (status, errno, h_errno, host),
+            (NssStatus::NotFound as libc::c_int, libc::ENOENT, 4, None)
+        );
+    }
+}
+
+/// Calls `gethostbyname4_r`, optionally providing storage for the first tuple like `getaddrinfo`.
+/// Returns the status, errno, h_errno, TTL and the names and addresses of the list.
+unsafe fn gethostbyname4(
+    name: &CStr,
+    first: Option<&mut CGaihAddrtuple>,
+    buflen: usize,
+) -> (
+    libc::c_int,
+    libc::c_int,
+    libc::c_int,
+    i32,
+    Vec<(String, IpAddr)>,
+) {
+    let mut pat = match first {
+        Some(first) => first as *mut CGaihAddrtuple,
+        None => std::ptr::null_mut(),
+    };
+    let mut buf = [0 as libc::c_char; 512];
+    let mut errno = 0;
+    let mut h_errno = 0;
+    let mut ttl = -1;
+
+    let status = _nss_hosts_gethostbyname4_r(
+        name.as_ptr(),
+        &mut pat,
+        buf.as_mut_ptr(),
+        buflen,
+        &mut errno,
+        &mut h_errno,
+        &mut ttl,
+    );
+
+    let mut tuples = vec![];
+    if status == SUCCESS {
+        let mut tuple = pat;
+        while !tuple.is_null() {
+            let octets = (*tuple)
+                .addr
+                .iter()
+                .flat_map(|a| a.to_ne_bytes())
+                .collect::<Vec<u8>>();
+            let addr = match (*tuple).family {
+                libc::AF_INET => IpAddr::from([octets[0], octets[1], octets[2], octets[3]]),
+                _ => {
+                    let mut v6 = [0u8; 16];
+                    v6.copy_from_slice(&octets);
+                    IpAddr::from(v6)
+                }
+            };
+            let name = CStr::from_ptr((*tuple).name).to_string_lossy().into_owned();
+            tuples.push((name, addr));
+            tuple = (*tuple).next;
+        }
+    }
+    (status, errno, h_errno, ttl, tuples)
+}
+
+#[test]
+fn gethostbyname4_returns_both_families() {
+    let name = CStr::from_bytes_with_nul(b"dual.example\0").unwrap();
+    let expected = vec![
+        ("canonical.example".to_string(), IpAddr::V4(V4)),
+        ("canonical.example".to_string(), IpAddr::V6(V6)),
+    ];
+
+    unsafe {
+        // The list is allocated in the buffer, with the shortest TTL of both lookups
+        assert_eq!(
+            gethostbyname4(name, None, 512),
+            (SUCCESS, 0, 0, 60, expected.clone())
+        );
+
+        // The first tuple is copied into storage provided by the caller
+        let mut first = std::mem::zeroed::<CGaihAddrtuple>();
+        assert_eq!(
+            gethostbyname4(name, Some(&mut first), 512),
+            (SUCCESS, 0, 0, 60, expected)
+        );
+        assert_eq!(first.family, libc::AF_INET);
+        assert!(!first.next.is_null());
+
+        // A too small buffer makes glibc retry with a larger one
+        assert_eq!(
+            gethostbyname4(name, None, 16),
+            (
+                NssStatus::TryAgain as libc::c_int,
+                libc::ERANGE,
+                -1,
+                -1,
+                vec![]
+            )
+        );
+
+        let unknown = CStr::from_bytes_with_nul(b"unknown.example\0").unwrap();
+        assert_eq!(
+            gethostbyname4(unknown, None, 512),
+            (
+                NssStatus::NotFound as libc::c_int,
+                libc::ENOENT,
+                4,
+                -1,
+                vec![]
+            )
+        );
+    }
+}
+
+#[test]
+fn mixed_host_is_split_per_family() {
+    let name = CStr::from_bytes_with_nul(b"dual.example\0").unwrap();
+    let gethostbyname2 = |family| unsafe {
+        hostent(512, |result, buf, buflen, errnop, h_errnop| {
+            _nss_hosts_gethostbyname2_r(
+                name.as_ptr(),
+                family,
+                result,
+                buf,
+                buflen,
+                errnop,
+                h_errnop,
+            )
+        })
+    };
+    let host = |family, addresses| {
+        Some(HostEnt {
+            name: "canonical.example".to_string(),
+            aliases: vec!["dual.example".to_string()],
+            family,
+            addresses,
+        })
+    };
+
+    assert_eq!(
+        gethostbyname2(libc::AF_INET),
+        (SUCCESS, 0, 0, host(libc::AF_INET, vec![IpAddr::V4(V4)]))
+    );
+    assert_eq!(
+        gethostbyname2(libc::AF_INET6),
+        (SUCCESS, 0, 0, host(libc::AF_INET6, vec![IpAddr::V6(V6)]))
+    );
+    // A hostent holds a single family, IPv4 is preferred
+    assert_eq!(
+        gethostbyname2(libc::AF_UNSPEC),
+        (SUCCESS, 0, 0, host(libc::AF_INET, vec![IpAddr::V4(V4)]))
+    );
+}
+
+#[test]
+fn gethostbyname3_reports_ttl_and_canonical_name() {
+    let name = CStr::from_bytes_with_nul(b"dual.example\0").unwrap();
+    let gethostbyname3 = |family| unsafe {
+        let mut ttl = -1;
+        let mut canon = None;
+        let (status, _, _, host) = hostent(512, |result, buf, buflen, errnop, h_errnop| {
+            let mut canonp = std::ptr::null();
+            let status = _nss_hosts_gethostbyname3_r(
+                name.as_ptr(),
+                family,
+                result,
+                buf,
+                buflen,
+                errnop,
+                h_errnop,
+                &mut ttl,
+                &mut canonp,
+            );
+            // Points into the buffer, which only lives during the call
+            if status == SUCCESS {
+                canon = Some(CStr::from_ptr(canonp).to_string_lossy().into_owned());
+            }
+            status
+        });
+        (status, ttl, canon, host.map(|host| host.name))
+    };
+
+    let canonical = "canonical.example".to_string();
+    assert_eq!(
+        gethostbyname3(libc::AF_INET),
+        (
+            SUCCESS,
+            300,
+            Some(canonical.clone()),
+            Some(canonical.clone())
+        )
+    );
+    assert_eq!(
+        gethostbyname3(libc::AF_INET6),
+        (SUCCESS, 60, Some(canonical.clone()), Some(canonical))
+    );
+}
+
+unsafe fn getcanonname(
+    name: &CStr,
+    buflen: usize,
+) -> (libc::c_int, libc::c_int, libc::c_int, Option<String>) {
+    let mut buf = [0 as libc::c_char; 512];
+    let mut result = std::ptr::null_mut();
+    let mut errno = 0;
+    let mut h_errno = 0;
+
+    let status = _nss_hosts_getcanonname_r(
+        name.as_ptr(),
+        buf.as_mut_ptr(),
+        buflen,
+        &mut result,
+        &mut errno,
+        &mut h_errno,
+    );
+    let canon = if status == SUCCESS {
+        Some(CStr::from_ptr(result).to_string_lossy().into_owned())
+    } else {
+        None
+    };
+    (status, errno, h_errno, canon)
+}
+
+#[test]
+fn getcanonname_defaults_to_the_host_name() {
+    unsafe {
+        let name = CStr::from_bytes_with_nul(b"dual.example\0").unwrap();
+        assert_eq!(
+            getcanonname(name, 512),
+            (SUCCESS, 0, 0, Some("canonical.example".to_string()))
+        );
+        assert_eq!(
+            getcanonname(name, 4),
+            (NssStatus::TryAgain as libc::c_int, libc::ERANGE, -1, None)
+        );
+
+        let unknown = CStr::from_bytes_with_nul(b"unknown.example\0").unwrap();
+        assert_eq!(
+            getcanonname(unknown, 512),
+            (NssStatus::NotFound as libc::c_int, libc::ENOENT, 4, None)
+        );
+    }
+}
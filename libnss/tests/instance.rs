@@ -0,0 +1,85 @@
+//! Backend instances shared by the `libnss_*_hooks!(name, Type = new)` macro forms
+
+mod common;
+
+use common::{getpw, passwd, SUCCESS};
+use libnss::interop::Response;
+use libnss::libnss_passwd_hooks;
+use libnss::passwd::{CPasswd, Passwd, PasswdBackend};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+struct Resolver {
+    server: String,
+}
+
+/// Constructing the backend needs another instance, as a backend resolving its server would
+struct Directory {
+    server: String,
+}
+
+impl Directory {
+    fn new() -> Self {
+        let resolver = libnss::interop::shared_instance(|| Resolver {
+            server: "ldap.example".to_string(),
+        });
+        Directory {
+            server: resolver.server.clone(),
+        }
+    }
+}
+
+libnss_passwd_hooks!(instance, Directory = Directory::new());
+
+impl PasswdBackend for Directory {
+    fn get_all_entries(&self) -> Response<Vec<Passwd>> {
+        Response::Success(vec![])
+    }
+
+    fn get_entry_by_uid(&self, uid: libc::uid_t) -> Response<Passwd> {
+        Response::Success(passwd(&self.server, uid))
+    }
+
+    fn get_entry_by_name(&self, _name: String) -> Response<Passwd> {
+        Response::NotFound
+    }
+}
+
+extern "C" {
+    fn _nss_instance_getpwuid_r(
+        uid: libc::uid_t,
+        result: *mut CPasswd,
+        buf: *mut libc::c_char,
+        buflen: libc::size_t,
+        errnop: *mut libc::c_int,
+    ) -> libc::c_int;
+}
+
+#[test]
+fn constructor_may_use_other_instances() {
+    // Run the lookup on another thread, so a deadlock fails the test instead of hanging it
+    let (done, finished) = mpsc::channel();
+    thread::spawn(move || unsafe {
+        let (status, _, entry) = getpw(256, |result, buf, buflen, errnop| {
+            _nss_instance_getpwuid_r(1000, result, buf, buflen, errnop)
+        });
+        done.send((status, entry)).unwrap();
+    });
+
+    assert_eq!(
+        finished.recv_timeout(Duration::from_secs(10)),
+        Ok((SUCCESS, Some(("ldap.example".to_string(), 1000))))
+    );
+}
+
+struct Counter(u32);
+
+#[test]
+fn instances_are_shared_by_type() {
+    let first = libnss::interop::shared_instance(|| Counter(1));
+    let second = libnss::interop::shared_instance(|| Counter(2));
+
+    assert!(std::ptr::eq(first, second));
+    assert_eq!(second.0, 1);
+}
@@ -0,0 +1,224 @@
+//! A module only implements the databases it supports, all others are unavailable
+
+mod common;
+
+use common::{getpw, passwd, NOTFOUND, SUCCESS, UNAVAIL};
+use libnss::group::{CGroup, Group};
+use libnss::interop::{EnumerationMode, Response};
+use libnss::libnss_module;
+use libnss::module::NssModule;
+use libnss::passwd::{CPasswd, Passwd};
+use std::ffi::CStr;
+use std::thread;
+
+#[derive(Default)]
+struct PasswdOnly;
+libnss_module!(partial, PasswdOnly);
+
+impl NssModule for PasswdOnly {
+    fn passwd_enumeration_mode(&self) -> EnumerationMode {
+        EnumerationMode::Thread
+    }
+
+    fn get_all_passwd(&self) -> Response<Vec<Passwd>> {
+        Response::Success(vec![passwd("alice", 1000), passwd("bob", 1001)])
+    }
+
+    fn get_passwd_by_name(&self, name: String) -> Response<Passwd> {
+        match name.as_str() {
+            "alice" => Response::Success(passwd("alice", 1000)),
+            _ => Response::NotFound,
+        }
+    }
+}
+
+extern "C" {
+    fn _nss_partial_setpwent() -> libc::c_int;
+    fn _nss_partial_endpwent() -> libc::c_int;
+    fn _nss_partial_getpwent_r(
+        result: *mut CPasswd,
+        buf: *mut libc::c_char,
+        buflen: libc::size_t,
+        errnop: *mut libc::c_int,
+    ) -> libc::c_int;
+    fn _nss_partial_getpwnam_r(
+        name: *const libc::c_char,
+        result: *mut CPasswd,
+        buf: *mut libc::c_char,
+        buflen: libc::size_t,
+        errnop: *mut libc::c_int,
+    ) -> libc::c_int;
+    fn _nss_partial_getpwuid_r(
+        uid: libc::uid_t,
+        result: *mut CPasswd,
+        buf: *mut libc::c_char,
+        buflen: libc::size_t,
+        errnop: *mut libc::c_int,
+    ) -> libc::c_int;
+    fn _nss_partial_getgrnam_r(
+        name: *const libc::c_char,
+        result: *mut CGroup,
+        buf: *mut libc::c_char,
+        buflen: libc::size_t,
+        errnop: *mut libc::c_int,
+    ) -> libc::c_int;
+    fn _nss_partial_setgrent() -> libc::c_int;
+    fn _nss_partial_initgroups_dyn(
+        name: *const libc::c_char,
+        skipgroup: libc::gid_t,
+        start: *mut libc::size_t,
+        size: *mut libc::size_t,
+        groupsp: *mut *mut libc::gid_t,
+        limit: libc::size_t,
+        errnop: *mut libc::c_int,
+    ) -> libc::c_int;
+}
+
+/// Calls an `initgroups_dyn` entry point for a user whose primary group is 10, returning the
+/// status and the resulting groups
+unsafe fn initgroups(
+    initgroups_dyn: unsafe extern "C" fn(
+        *const libc::c_char,
+        libc::gid_t,
+        *mut libc::size_t,
+        *mut libc::size_t,
+        *mut *mut libc::gid_t,
+        libc::size_t,
+        *mut libc::c_int,
+    ) -> libc::c_int,
+    name: &CStr,
+) -> (libc::c_int, Vec<libc::gid_t>) {
+    // glibc passes the primary group first and skips it in the module's answer
+    let mut size = 4;
+    let mut groups = libc::malloc(size * std::mem::size_of::<libc::gid_t>()) as *mut libc::gid_t;
+    *groups = 10;
+    let mut start = 1;
+    let mut errno = 0;
+
+    let status = initgroups_dyn(
+        name.as_ptr(),
+        10,
+        &mut start,
+        &mut size,
+        &mut groups,
+        64,
+        &mut errno,
+    );
+    let result = std::slice::from_raw_parts(groups, start).to_vec();
+    libc::free(groups as *mut libc::c_void);
+    (status, result)
+}
+
+unsafe fn getpwnam(name: &CStr) -> (libc::c_int, Option<u32>) {
+    let (status, _, entry) = getpw(256, |result, buf, buflen, errnop| {
+        _nss_partial_getpwnam_r(name.as_ptr(), result, buf, buflen, errnop)
+    });
+    (status, entry.map(|(_, uid)| uid))
+}
+
+unsafe fn getpwent() -> (libc::c_int, Option<u32>) {
+    let (status, _, entry) = getpw(256, |result, buf, buflen, errnop| {
+        _nss_partial_getpwent_r(result, buf, buflen, errnop)
+    });
+    (status, entry.map(|(_, uid)| uid))
+}
+
+#[test]
+fn implemented_lookups() {
+    unsafe {
+        let alice = CStr::from_bytes_with_nul(b"alice\0").unwrap();
+        assert_eq!(getpwnam(alice), (SUCCESS, Some(1000)));
+
+        let carol = CStr::from_bytes_with_nul(b"carol\0").unwrap();
+        assert_eq!(getpwnam(carol), (NOTFOUND, None));
+
+        // The enumeration defaults to the entries of get_all_passwd
+        assert_eq!(_nss_partial_setpwent(), SUCCESS);
+        let mut uids = vec![];
+        while let (SUCCESS, Some(uid)) = getpwent() {
+            uids.push(uid);
+        }
+        assert_eq!(_nss_partial_endpwent(), SUCCESS);
+        assert_eq!(uids, vec![1000, 1001]);
+    }
+}
+
+#[test]
+fn unimplemented_lookups_are_unavailable() {
+    unsafe {
+        let (status, _, _) = getpw(256, |result, buf, buflen, errnop| {
+            _nss_partial_getpwuid_r(1000, result, buf, buflen, errnop)
+        });
+        assert_eq!(status, UNAVAIL);
+
+        let mut group = std::mem::MaybeUninit::<CGroup>::zeroed();
+        let mut buf = [0 as libc::c_char; 256];
+        let mut errno = 0;
+
+        let name = CStr::from_bytes_with_nul(b"users\0").unwrap();
+        let status = _nss_partial_getgrnam_r(
+            name.as_ptr(),
+            group.as_mut_ptr(),
+            buf.as_mut_ptr(),
+            buf.len(),
+            &mut errno,
+        );
+        assert_eq!(status, UNAVAIL);
+        assert_eq!(_nss_partial_setgrent(), UNAVAIL);
+        assert_eq!(
+            initgroups(_nss_partial_initgroups_dyn, name),
+            (UNAVAIL, vec![10])
+        );
+    }
+}
+
+#[test]
+fn enumeration_mode_per_database() {
+    // Only the thread mode implicitly opens an enumeration for a thread without one
+    let entry = thread::spawn(|| unsafe { getpwent() });
+    assert_eq!(entry.join().unwrap(), (SUCCESS, Some(1000)));
+}
+
+#[derive(Default)]
+struct GroupsOnly;
+libnss_module!(groups, GroupsOnly);
+
+impl NssModule for GroupsOnly {
+    fn get_all_groups(&self) -> Response<Vec<Group>> {
+        let group = |name: &str, gid, members: &[&str]| Group {
+            name: name.to_string(),
+            passwd: "x".to_string(),
+            gid,
+            members: members.iter().map(|member| member.to_string()).collect(),
+        };
+
+        Response::Success(vec![
+            group("users", 100, &["bob"]),
+            group("staff", 50, &["alice", "bob"]),
+            group("wheel", 10, &["alice"]),
+        ])
+    }
+}
+
+extern "C" {
+    fn _nss_groups_initgroups_dyn(
+        name: *const libc::c_char,
+        skipgroup: libc::gid_t,
+        start: *mut libc::size_t,
+        size: *mut libc::size_t,
+        groupsp: *mut *mut libc::gid_t,
+        limit: libc::size_t,
+        errnop: *mut libc::c_int,
+    ) -> libc::c_int;
+}
+
+#[test]
+fn initgroups_defaults_to_group_members() {
+    unsafe {
+        let name = CStr::from_bytes_with_nul(b"alice\0").unwrap();
+        assert_eq!(
+            initgroups(_nss_groups_initgroups_dyn, name),
+            (SUCCESS, vec![10, 50])
+        );
+    }
+}
@@ -0,0 +1,172 @@
+//! Panicking hooks must not leave the generated entry points unusable
+
+mod common;
+
+use common::{getpw, passwd, SUCCESS, UNAVAIL};
+use libnss::interop::{
+    Enumeration, EnumerationCursor, EnumerationMode, EnumerationState, NssStatus, Response,
+};
+use libnss::libnss_passwd_hooks;
+use libnss::passwd::{CPasswd, Passwd, PasswdHooks};
+use std::cell::RefCell;
+use std::ffi::CStr;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static PANIC_ON_OPEN: AtomicBool = AtomicBool::new(false);
+static PANIC_ON_NAME: AtomicBool = AtomicBool::new(false);
+
+struct PanickingPasswd;
+libnss_passwd_hooks!(poison, PanickingPasswd);
+
+impl PasswdHooks for PanickingPasswd {
+    fn get_all_entries() -> Response<Vec<Passwd>> {
+        Response::Success(vec![])
+    }
+
+    // The second entry panics while it is pulled by getpwent_r
+    fn open_enumeration() -> Response<Enumeration<Passwd>> {
+        if PANIC_ON_OPEN.load(Ordering::SeqCst) {
+            panic!("open_enumeration");
+        }
+
+        Response::Success(Box::new((0..3).map(|uid| {
+            if uid == 1 {
+                panic!("enumeration");
+            }
+            Response::Success(passwd("user", uid))
+        })))
+    }
+
+    fn get_entry_by_uid(uid: libc::uid_t) -> Response<Passwd> {
+        Response::Success(passwd("user", uid))
+    }
+
+    fn get_entry_by_name(name: String) -> Response<Passwd> {
+        if PANIC_ON_NAME.load(Ordering::SeqCst) {
+            panic!("get_entry_by_name");
+        }
+
+        Response::Success(passwd(&name, 1000))
+    }
+}
+
+extern "C" {
+    fn _nss_poison_setpwent() -> libc::c_int;
+    fn _nss_poison_endpwent() -> libc::c_int;
+    fn _nss_poison_getpwent_r(
+        result: *mut CPasswd,
+        buf: *mut libc::c_char,
+        buflen: libc::size_t,
+        errnop: *mut libc::c_int,
+    ) -> libc::c_int;
+    fn _nss_poison_getpwnam_r(
+        name: *const libc::c_char,
+        result: *mut CPasswd,
+        buf: *mut libc::c_char,
+        buflen: libc::size_t,
+        errnop: *mut libc::c_int,
+    ) -> libc::c_int;
+}
+
+unsafe fn getpwent() -> (libc::c_int, Option<u32>) {
+    let (status, _, entry) = getpw(256, |result, buf, buflen, errnop| {
+        _nss_poison_getpwent_r(result, buf, buflen, errnop)
+    });
+    (status, entry.map(|(_, uid)| uid))
+}
+
+unsafe fn getpwnam(name: &CStr) -> (libc::c_int, libc::c_int, Option<String>) {
+    let (status, errno, entry) = getpw(256, |result, buf, buflen, errnop| {
+        _nss_poison_getpwnam_r(name.as_ptr(), result, buf, buflen, errnop)
+    });
+    (status, errno, entry.map(|(name, _)| name))
+}
+
+// All passwd entry points share one enumeration, so the scenarios run in sequence
+#[test]
+fn panicking_passwd_hooks() {
+    unsafe {
+        let name = CStr::from_bytes_with_nul(b"alice\0").unwrap();
+
+        // A panicking lookup is reported as unavailable, later lookups are unaffected
+        PANIC_ON_NAME.store(true, Ordering::SeqCst);
+        assert_eq!(getpwnam(name), (UNAVAIL, libc::ENOENT, None));
+        PANIC_ON_NAME.store(false, Ordering::SeqCst);
+        assert_eq!(getpwnam(name), (SUCCESS, 0, Some("alice".to_string())));
+
+        // A panicking open leaves no enumeration behind
+        PANIC_ON_OPEN.store(true, Ordering::SeqCst);
+        assert_eq!(_nss_poison_setpwent(), UNAVAIL);
+        assert_eq!(getpwent(), (UNAVAIL, None));
+        PANIC_ON_OPEN.store(false, Ordering::SeqCst);
+
+        // A panic while pulling an entry ends the enumeration
+        assert_eq!(_nss_poison_setpwent(), SUCCESS);
+        assert_eq!(getpwent(), (SUCCESS, Some(0)));
+        assert_eq!(getpwent(), (UNAVAIL, None));
+        assert_eq!(getpwent(), (UNAVAIL, None));
+
+        // Enumerating and lookups work again afterwards
+        assert_eq!(_nss_poison_setpwent(), SUCCESS);
+        assert_eq!(getpwent(), (SUCCESS, Some(0)));
+        assert_eq!(_nss_poison_endpwent(), SUCCESS);
+        assert_eq!(getpwnam(name), (SUCCESS, 0, Some("alice".to_string())));
+    }
+}
+
+static PANIC_ON_CLONE: AtomicBool = AtomicBool::new(false);
+
+/// Entry which can panic when cloned while the enumeration state is locked
+#[derive(Debug)]
+struct Fragile(u32);
+
+impl Clone for Fragile {
+    fn clone(&self) -> Self {
+        if PANIC_ON_CLONE.load(Ordering::SeqCst) {
+            panic!("clone");
+        }
+        Fragile(self.0)
+    }
+}
+
+fn open_fragile() -> Response<Enumeration<Fragile>> {
+    Response::Success(Box::new((0..3).map(|i| Response::Success(Fragile(i)))))
+}
+
+thread_local! {
+    static FRAGILE_THREAD: RefCell<EnumerationCursor<Fragile>> = RefCell::new(EnumerationCursor::new());
+}
+
+#[test]
+fn poisoned_state_is_reset() {
+    let state = EnumerationState::new(EnumerationMode::Process, open_fragile, &FRAGILE_THREAD);
+    assert_eq!(state.set(), NssStatus::Success);
+
+    let errno = 0;
+    PANIC_ON_CLONE.store(true, Ordering::SeqCst);
+    let get = AssertUnwindSafe(|| unsafe { state.get(&errno, |response| response.to_status()) });
+    assert!(panic::catch_unwind(get).is_err());
+    PANIC_ON_CLONE.store(false, Ordering::SeqCst);
+
+    // The poisoned state is reported once, then usable again
+    assert_eq!(
+        unsafe { state.get(&errno, |response| response.to_status()) },
+        NssStatus::Unavail
+    );
+    assert_eq!(state.end(), NssStatus::Success);
+    assert_eq!(state.set(), NssStatus::Success);
+
+    let mut entries = vec![];
+    while unsafe {
+        state.get(&errno, |response| match response {
+            Response::Success(Fragile(i)) => {
+                entries.push(i);
+                NssStatus::Success
+            }
+            response => response.to_status(),
+        })
+    } == NssStatus::Success
+    {}
+    assert_eq!(entries, vec![0, 1, 2]);
+}